@@ -0,0 +1,59 @@
+use crate::puzzle::*;
+
+/// colours of the sides of the pieces of an edge matching puzzle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeColors {
+    /// colour of the side of each point, as written
+    colors: Vec<String>,
+}
+impl EdgeColors {
+    /// parse `point=colour` for every point, separated by whitespace, with `#` comments.
+    /// the pieces may start anywhere on the board, they are only named after where they start
+    pub fn parse(puzzle: &impl Puzzle, text: &str) -> Result<Self, String> {
+        let mut colors = vec![None; puzzle.num_points()];
+        let tokens = text
+            .lines()
+            .flat_map(|line| line.split('#').next().unwrap().split_whitespace());
+        for token in tokens {
+            let (name, color) = token
+                .split_once('=')
+                .ok_or_else(|| format!("invalid colour {token:?}, expected <point>=<colour>"))?;
+            let point =
+                puzzle_find_point(puzzle, name).ok_or_else(|| format!("unknown point {name:?}"))?;
+            if colors[point.0].replace(color.to_owned()).is_some() {
+                return Err(format!("point {name} has more than one colour"));
+            }
+        }
+        let colors = puzzle_points(puzzle)
+            .zip(colors)
+            .map(|(point, color)| {
+                color.ok_or_else(|| format!("point {} has no colour", puzzle.format_point(point)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { colors })
+    }
+
+    /// whether the sides of two points can be put together
+    pub fn fits(&self, point_a: PointKey, point_b: PointKey) -> bool {
+        self.colors[point_a.0] == self.colors[point_b.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_colors() {
+        let puzzle = SquarePuzzle::new(1, 3);
+        let point = |name| puzzle_find_point(&puzzle, name).unwrap();
+        let colors =
+            EdgeColors::parse(&puzzle, "a1>=red a2<=blue # middle\na2>=red a3<=red").unwrap();
+        assert!(colors.fits(point("a1>"), point("a2>")));
+        assert!(!colors.fits(point("a1>"), point("a2<")));
+
+        assert!(EdgeColors::parse(&puzzle, "a1>=red a2<=blue a2>=red").is_err());
+        assert!(EdgeColors::parse(&puzzle, "a1>=red a1>=red a2<=blue a2>=red a3<=red").is_err());
+        assert!(EdgeColors::parse(&puzzle, "a1> a2<=blue a2>=red a3<=red").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::puzzle::*;
+
+/// a piece, or a point of a piece when its orientation matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Place {
+    Piece(PieceKey),
+    Point(PointKey),
+}
+
+/// a rule about where pieces go in the other assemblies, written one per line:
+/// - `b2=>d4` piece b2 goes in place of d4, `b2^=>d4>` also fixes how it is turned
+/// - `!b2=>d4` piece b2 doesn't go in place of d4, `!b2^=>d4>` only forbids that one way of turning it
+/// - `b2 moves` piece b2 doesn't stay in its own place, `all moves` for every piece
+/// - `b2 turns` piece b2 is turned or turned over, `b2 turns 90` turned 90 degrees clockwise without turning it over,
+///   `all turns` for every piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementRule {
+    Goes {
+        src: Place,
+        dest: Place,
+    },
+    DoesNotGo {
+        src: Place,
+        dest: Place,
+    },
+    /// `None` for every piece
+    Moves(Option<PieceKey>),
+    /// `None` for every piece, turned by any amount if `degrees` is `None`
+    Turns {
+        piece: Option<PieceKey>,
+        degrees: Option<u32>,
+    },
+}
+impl PlacementRule {
+    /// source pieces and points are named on `puzzle`, destinations on `dest`
+    pub fn parse<P: Puzzle>(puzzle: &P, dest: &P, rule: &str) -> Result<Self, String> {
+        let piece = |name: &str| match name {
+            "all" => Ok(None),
+            _ => puzzle_find_piece(puzzle, name)
+                .map(Some)
+                .ok_or_else(|| format!("unknown piece {name:?}")),
+        };
+        let words = rule.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            [name, "moves"] => Ok(Self::Moves(piece(name)?)),
+            [name, "turns"] => Ok(Self::Turns {
+                piece: piece(name)?,
+                degrees: None,
+            }),
+            [name, "turns", degrees] => {
+                let degrees = degrees
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid degrees {degrees:?}"))?;
+                Ok(Self::Turns {
+                    piece: piece(name)?,
+                    degrees: Some(degrees % 360),
+                })
+            }
+            [placement] => {
+                let (forbid, placement) = match placement.strip_prefix('!') {
+                    Some(placement) => (true, placement),
+                    None => (false, placement),
+                };
+                let (src, dest_name) = placement
+                    .split_once("=>")
+                    .ok_or_else(|| format!("invalid rule {rule:?}"))?;
+                let src = find_place(puzzle, src)?;
+                let dest = find_place(dest, dest_name)?;
+                if matches!((src, dest), (Place::Piece(_), Place::Point(_))) {
+                    return Err(format!("piece in {rule:?} can only go to a piece"));
+                }
+                Ok(if forbid {
+                    Self::DoesNotGo { src, dest }
+                } else {
+                    Self::Goes { src, dest }
+                })
+            }
+            _ => Err(format!("invalid rule {rule:?}")),
+        }
+    }
+
+    /// parse a rule on each line, skipping blank lines and comments starting with `#`
+    pub fn parse_lines<P: Puzzle>(puzzle: &P, dest: &P, text: &str) -> Result<Vec<Self>, String> {
+        text.lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| Self::parse(puzzle, dest, line))
+            .collect()
+    }
+}
+
+/// a label for pieces, like the part of a picture they show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceLabels {
+    labels: Vec<Option<String>>,
+}
+impl PieceLabels {
+    /// parse `piece=label` separated by whitespace, with `#` comments. pieces may be left without a label
+    pub fn parse(puzzle: &impl Puzzle, text: &str) -> Result<Self, String> {
+        let mut labels = vec![None; puzzle.num_pieces()];
+        let tokens = text
+            .lines()
+            .flat_map(|line| line.split('#').next().unwrap().split_whitespace());
+        for token in tokens {
+            let (name, label) = token
+                .split_once('=')
+                .ok_or_else(|| format!("invalid label {token:?}, expected <piece>=<label>"))?;
+            let piece =
+                puzzle_find_piece(puzzle, name).ok_or_else(|| format!("unknown piece {name:?}"))?;
+            if labels[piece.0].replace(label.to_owned()).is_some() {
+                return Err(format!("piece {name} has more than one label"));
+            }
+        }
+        Ok(Self { labels })
+    }
+
+    pub fn label(&self, piece: PieceKey) -> Option<&str> {
+        self.labels[piece.0].as_deref()
+    }
+
+    /// labels that are used more often here than in `available`, with how often they are used in each
+    pub fn missing_from(&self, available: &Self) -> Vec<(&str, usize, usize)> {
+        fn counts(labels: &PieceLabels) -> HashMap<&str, usize> {
+            labels.labels.iter().flatten().map(String::as_str).counts()
+        }
+        let (needed, available) = (counts(self), counts(available));
+        needed
+            .into_iter()
+            .map(|(label, count)| (label, count, available.get(label).copied().unwrap_or(0)))
+            .filter(|&(_, needed, available)| needed > available)
+            .sorted()
+            .collect()
+    }
+}
+
+fn find_place(puzzle: &impl Puzzle, name: &str) -> Result<Place, String> {
+    puzzle_find_piece(puzzle, name)
+        .map(Place::Piece)
+        .or_else(|| puzzle_find_point(puzzle, name).map(Place::Point))
+        .ok_or_else(|| format!("unknown piece or point {name:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rules() {
+        let puzzle = SquarePuzzle::new(3, 3);
+        let parse = |rule| PlacementRule::parse(&puzzle, &puzzle, rule);
+        let piece = |name| puzzle_find_piece(&puzzle, name).unwrap();
+        let point = |name| puzzle_find_point(&puzzle, name).unwrap();
+        assert_eq!(
+            parse("b2=>c3"),
+            Ok(PlacementRule::Goes {
+                src: Place::Piece(piece("b2")),
+                dest: Place::Piece(piece("c3")),
+            })
+        );
+        assert_eq!(
+            parse("!a2>=>c2<"),
+            Ok(PlacementRule::DoesNotGo {
+                src: Place::Point(point("a2>")),
+                dest: Place::Point(point("c2<")),
+            })
+        );
+        assert_eq!(parse("all moves"), Ok(PlacementRule::Moves(None)));
+        assert_eq!(
+            parse("b2 turns 450"),
+            Ok(PlacementRule::Turns {
+                piece: Some(piece("b2")),
+                degrees: Some(90),
+            })
+        );
+        assert!(parse("b2=>c2>").is_err());
+        assert!(parse("z9 moves").is_err());
+        assert!(parse("b2 jumps").is_err());
+        assert!(parse("b2 turns left").is_err());
+
+        let rules = PlacementRule::parse_lines(
+            &puzzle,
+            &puzzle,
+            "# no piece stays\nall moves\n\nb2 turns # any amount\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+    }
+
+    #[test]
+    fn parse_labels() {
+        let puzzle = SquarePuzzle::new(2, 2);
+        let piece = |name| puzzle_find_piece(&puzzle, name).unwrap();
+        let labels = PieceLabels::parse(&puzzle, "a1=sky a2=sky # top\nb1=ground").unwrap();
+        assert_eq!(labels.label(piece("a2")), Some("sky"));
+        assert_eq!(labels.label(piece("b2")), None);
+
+        let target = PieceLabels::parse(&puzzle, "a1=ground b1=ground b2=sky").unwrap();
+        assert_eq!(target.missing_from(&labels), vec![("ground", 2, 1)]);
+        assert!(labels.missing_from(&labels).is_empty());
+
+        assert!(PieceLabels::parse(&puzzle, "a1=sky a1=sky").is_err());
+        assert!(PieceLabels::parse(&puzzle, "c1=sky").is_err());
+        assert!(PieceLabels::parse(&puzzle, "a1").is_err());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod coloring;
+pub mod constraints;
+pub mod puzzle;
+pub mod render;
+pub mod sat;
+pub mod shapes;
+pub mod solution;
@@ -1,22 +1,114 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::hash::Hash;
+use std::process::ExitCode;
 use std::time::Instant;
 
-mod puzzle;
-mod sat;
+mod options;
 use itertools::Itertools;
-use puzzle::*;
-use sat::*;
+use jigsaw_doubler::coloring::*;
+use jigsaw_doubler::constraints::*;
+use jigsaw_doubler::puzzle::*;
+use jigsaw_doubler::render::*;
+use jigsaw_doubler::sat::*;
+use jigsaw_doubler::shapes::*;
+use jigsaw_doubler::solution::*;
+use options::*;
 use varisat::Lit;
 
-fn make_puzzle() -> impl Puzzle {
-    SquarePuzzle::new(5, 5)
+fn main() -> ExitCode {
+    let start_time = Instant::now();
+    let args = std::env::args().skip(1).collect_vec();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprint!("{err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    // only square boards can be put together into a different outline
+    let dest = match options.dest {
+        Some(BoardOption::Square { rows, cols }) => Ok(Some(square_board(rows, cols, &options))),
+        Some(BoardOption::Mask(ref art)) => SquarePuzzle::parse_mask(art).map(Some),
+        _ => Ok(None),
+    };
+    let result = dest.and_then(|dest| match options.board {
+        BoardOption::Square { rows, cols } => run_board(
+            square_board(rows, cols, &options),
+            dest,
+            &options,
+            start_time,
+        ),
+        BoardOption::Mask(ref art) => SquarePuzzle::parse_mask(art)
+            .and_then(|puzzle| run_board(puzzle, dest, &options, start_time)),
+        BoardOption::Voronoi { rows, cols } => {
+            TilingPuzzle::voronoi(rows, cols, options.jitter, options.seed)
+                .and_then(|puzzle| run_board(puzzle, None, &options, start_time))
+        }
+        BoardOption::Cairo { rows, cols } => TilingPuzzle::cairo(rows, cols)
+            .and_then(|puzzle| run_board(puzzle, None, &options, start_time)),
+        BoardOption::Rhombille { rows, cols } => run_board(
+            TilingPuzzle::rhombille(rows, cols),
+            None,
+            &options,
+            start_time,
+        ),
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
-fn main() {
-    let start_time = Instant::now();
-    let puzzle = make_puzzle();
-    JigsawDoubler::run(puzzle, start_time);
+/// a rectangle with its first corner kept in place to rule out turned copies of each solution,
+/// unless the search rules them out itself
+fn square_board(rows: usize, cols: usize, options: &Options) -> SquarePuzzle {
+    // an isolated corner would lose the solutions that put it in another corner
+    let one_assembly =
+        options.colors.is_some() || options.check.is_some() || options.extend.is_some();
+    if one_assembly || options.turns_matter() {
+        SquarePuzzle::rectangle(rows, cols)
+    } else {
+        SquarePuzzle::new(rows, cols)
+    }
+}
+
+/// draw the board if asked, then search it or check a cut of it.
+/// `dest` is the board for the second assembly, if it has a different outline
+fn run_board<P: PuzzleGeometry + Clone>(
+    puzzle: P,
+    dest: Option<P>,
+    options: &Options,
+    start_time: Instant,
+) -> Result<(), String> {
+    if let Some(path) = &options.svg {
+        fs::write(path, board_svg(&puzzle))
+            .map_err(|err| format!("could not write svg {path:?}: {err}"))?;
+    }
+    if let Some(text) = &options.colors {
+        return match dest {
+            Some(_) => Err("edge matching is not supported with a destination board".to_owned()),
+            None => JigsawDoubler::match_colors(puzzle, options, text, start_time),
+        };
+    }
+    match (&options.check, &options.extend, dest) {
+        (Some(_), _, Some(_)) | (_, Some(_), Some(_)) => {
+            Err("checking is not supported with a destination board".to_owned())
+        }
+        (Some(text), _, None) => JigsawDoubler::check(puzzle, options, text, start_time),
+        (None, Some(text), None) => JigsawDoubler::extend(puzzle, options, text, start_time),
+        (None, None, dest) => {
+            let dest = dest.unwrap_or_else(|| puzzle.clone());
+            JigsawDoubler::run(puzzle, dest, options, start_time)
+        }
+    }
 }
 
 struct MatchingVars<T>(HashMap<(T, T), Lit>);
@@ -57,16 +149,458 @@ impl<T: Eq + Hash, U: Eq + Hash> TableVars<T, U> {
     }
 }
 
-struct JigsawDoubler<P> {
-    puzzle: P,
-    sat: SatProblem,
+/// one way of putting a source piece in place of a destination piece
+struct Placement {
+    /// `arbitrary_point_on_piece` of the source piece
+    src_point: PointKey,
+    dest_point: PointKey,
+    /// turned over, so points go around the piece in the opposite direction
+    flipped: bool,
+    var: Lit,
+}
+
+/// variables for one rearrangement of the pieces, other than the original
+struct Assembly {
+    placements: Vec<Placement>,
+    /// whether a source point lands on a destination point, by any placement
     point_dest_vars: TableVars<PointKey, PointKey>,
     point_dest_adjacent_vars: MatchingVars<PointKey>,
     edge_matching_vars: MatchingVars<EdgeKey>,
 }
-impl<P: Puzzle> JigsawDoubler<P> {
-    pub fn run(puzzle: P, start_time: Instant) {
-        let mut s = Self::new(puzzle);
+impl Assembly {
+    fn new() -> Self {
+        Self {
+            placements: vec![],
+            point_dest_vars: TableVars::new(),
+            point_dest_adjacent_vars: MatchingVars::new(),
+            edge_matching_vars: MatchingVars::new(),
+        }
+    }
+}
+
+struct JigsawDoubler<P> {
+    /// board of the original assembly, which the pieces and edges are named after
+    puzzle: P,
+    /// board the other assemblies are put together on, usually the same as `puzzle`
+    dest: P,
+    sat: SatProblem,
+    /// whether pieces may be turned over in the other assemblies
+    flip: bool,
+    /// every assembly other than the original
+    assemblies: Vec<Assembly>,
+    /// whether each edge has a tab and a blank that must fit together in all assemblies
+    tabs: bool,
+    /// whether the tab of each edge is on its `arbitrary_point_on_edge`
+    tab_vars: Vec<Lit>,
+    /// pieces with a number of points have exactly a number of tabs
+    tab_counts: Vec<(usize, usize)>,
+    /// whether tabs and blanks alternate around pieces with an even number of points
+    alternate_tabs: bool,
+    /// whether the sides of two points on different edges have the same shape,
+    /// only needed with more than two assemblies
+    same_shape_vars: MatchingVars<PointKey>,
+    /// number of edges that stay together instead of matching another edge
+    unmatched: usize,
+    /// whether `unmatched` is only an upper bound
+    unmatched_at_most: bool,
+    /// edges that are allowed to be unmatched, `None` for all edges
+    unmatched_edges: Option<Vec<EdgeKey>>,
+    /// source piece that started in the place of each destination piece,
+    /// `None` if the destination board has pieces in other places
+    homes: Option<Vec<PieceKey>>,
+    /// pieces move in cycles at least this long
+    min_cycle: usize,
+    /// pieces move in cycles at most this long
+    max_cycle: Option<usize>,
+    /// the pieces that move all move in one cycle
+    single_cycle: bool,
+    /// number of assemblies before the ones searched for, including the original
+    known_assemblies: usize,
+    /// turns and mirrors of the whole board, as in `board_symmetries`, that take a solution of one assembly
+    /// to another one that is ruled out with it
+    symmetries: Vec<(Vec<PointKey>, bool)>,
+}
+impl<P: PuzzleGeometry + Clone> JigsawDoubler<P> {
+    pub fn run(puzzle: P, dest: P, options: &Options, start_time: Instant) -> Result<(), String> {
+        if dest.num_pieces() != puzzle.num_pieces() || dest.num_edges() != puzzle.num_edges() {
+            return Err(format!(
+                "the destination board has {} pieces and {} edges, but the board has {} and {}",
+                dest.num_pieces(),
+                dest.num_edges(),
+                puzzle.num_pieces(),
+                puzzle.num_edges()
+            ));
+        }
+        let (mut s, mut first_solution) = if options.fewest_unmatched {
+            let (s, solution) = Self::fewest_unmatched(puzzle, dest, options, start_time)?;
+            (s, Some(solution))
+        } else {
+            let s = Self::configure(puzzle, dest, options, options.unmatched)?;
+            println!("constraints configured, starting solve");
+            (s, None)
+        };
+        if let Some(path) = &options.opb {
+            return s.write_opb(path, options.optimize);
+        }
+        if let Some(path) = &options.dimacs {
+            return s.write_dimacs(path);
+        }
+        if let Some(objective) = options.optimize {
+            first_solution = s.optimize(objective)?;
+        }
+        if options.interactive {
+            return s.explore();
+        }
+
+        let mut last = start_time;
+        let mut count = 0;
+        while let Some(solution) = first_solution
+            .take()
+            .map_or_else(|| s.sat.try_solve(), |solution| Ok(Some(solution)))?
+        {
+            let now = Instant::now();
+            count += 1;
+            println!(
+                "found solution {} in {} ({} total)",
+                count,
+                humantime::format_duration(now - last),
+                humantime::format_duration(now - start_time)
+            );
+            s.print_solution(&solution);
+            if let Some(prefix) = &options.cut {
+                s.write_cuts(&solution, &format!("{prefix}{count}"))?;
+            }
+            println!();
+            s.add_prior_solution(&solution);
+            last = now;
+        }
+
+        let now = Instant::now();
+        println!(
+            "no more solutions. found {} solutions in {}",
+            count,
+            humantime::format_duration(now - start_time)
+        );
+        Ok(())
+    }
+    fn print_solution(&self, solution: &SatSolution) {
+        for assembly in 0..self.assemblies.len() {
+            self.print_point_dest(solution, assembly);
+            if self.flip {
+                self.print_flipped_pieces(solution, assembly);
+            }
+            self.print_edge_matching(solution, assembly);
+            if self.homes.is_some() {
+                self.print_cycle_type(solution, assembly);
+            }
+        }
+        if self.tabs {
+            self.print_tabs(solution);
+        }
+        if self.unmatched > 0 {
+            self.print_unmatched_edges(solution);
+        }
+    }
+    /// read placement rules from stdin, comma separated on each line, and show a solution that follows them.
+    /// the rules are assumed for one solve only, so the search isn't built again for each line
+    fn explore(&mut self) -> Result<(), String> {
+        println!("enter placement rules separated by commas, like `b2=>c3, a1 moves`");
+        for line in std::io::stdin().lines() {
+            let line = line.map_err(|err| format!("could not read input: {err}"))?;
+            let rules = line
+                .split(',')
+                .map(str::trim)
+                .filter(|rule| !rule.is_empty())
+                .collect_vec();
+            // each clause of a rule is assumed through one var, with the rule it came from
+            let mut assumptions = vec![];
+            let mut valid = true;
+            for (i, rule) in rules.iter().enumerate() {
+                let clauses = PlacementRule::parse(&self.puzzle, &self.dest, rule)
+                    .and_then(|rule| self.placement_rule_clauses(rule));
+                match clauses {
+                    Ok(clauses) => {
+                        for clause in clauses {
+                            let var = match clause[..] {
+                                [var] => var,
+                                _ => self.sat.or_var(&clause),
+                            };
+                            assumptions.push((var, i));
+                        }
+                    }
+                    Err(err) => {
+                        println!("{err}");
+                        valid = false;
+                    }
+                }
+            }
+            if !valid {
+                continue;
+            }
+            let vars = assumptions.iter().map(|&(var, _)| var).collect_vec();
+            match self.sat.solve_with_assumptions(&vars)? {
+                SolveResult::Solution(solution) => self.print_solution(&solution),
+                SolveResult::Failed(failed) if failed.is_empty() => {
+                    println!("no solutions with any rules")
+                }
+                SolveResult::Failed(failed) => {
+                    let conflicting = assumptions
+                        .iter()
+                        .filter(|(var, _)| failed.contains(var))
+                        .map(|&(_, i)| rules[i])
+                        .unique()
+                        .join(", ");
+                    println!("no solutions with {conflicting}");
+                }
+            }
+            println!();
+        }
+        Ok(())
+    }
+    /// describe the main vars for `write_dimacs`, counter vars are already described
+    fn name_vars(&mut self) {
+        for (assembly, vars) in self.assemblies.iter().enumerate() {
+            let label = self.label(assembly);
+            let (puzzle, dest) = (&self.puzzle, &self.dest);
+            for placement in &vars.placements {
+                self.sat.name_var(placement.var, || {
+                    format!(
+                        "placement{label}: {}=>{}{}",
+                        puzzle.format_point(placement.src_point),
+                        dest.format_point(placement.dest_point),
+                        if placement.flipped { " flipped" } else { "" }
+                    )
+                });
+            }
+            for (&(src_point, dest_point), &var) in &vars.point_dest_vars.0 {
+                self.sat.name_var(var, || {
+                    format!(
+                        "point dest{label}: {}=>{}",
+                        puzzle.format_point(src_point),
+                        dest.format_point(dest_point)
+                    )
+                });
+            }
+            for (&(point_a, point_b), &var) in &vars.point_dest_adjacent_vars.0 {
+                self.sat.name_var(var, || {
+                    format!(
+                        "point adjacent{label}: {} {}",
+                        puzzle.format_point(point_a),
+                        puzzle.format_point(point_b)
+                    )
+                });
+            }
+            for (&(edge_a, edge_b), &var) in &vars.edge_matching_vars.0 {
+                self.sat.name_var(var, || {
+                    format!(
+                        "edge matching{label}: {}={}",
+                        puzzle.format_edge(edge_a),
+                        puzzle.format_edge(edge_b)
+                    )
+                });
+            }
+        }
+        for (&(point_a, point_b), &var) in &self.same_shape_vars.0 {
+            let puzzle = &self.puzzle;
+            self.sat.name_var(var, || {
+                format!(
+                    "same shape: {} {}",
+                    puzzle.format_point(point_a),
+                    puzzle.format_point(point_b)
+                )
+            });
+        }
+        for (edge, &var) in puzzle_edges(&self.puzzle).zip(&self.tab_vars) {
+            let puzzle = &self.puzzle;
+            let point = puzzle.arbitrary_point_on_edge(edge);
+            self.sat
+                .name_var(var, || format!("tab: {}", puzzle.format_point(point)));
+        }
+    }
+    /// write the search to a DIMACS CNF file, projected onto where the pieces go
+    fn write_dimacs(&self, path: &str) -> Result<(), String> {
+        let projection = self
+            .assemblies
+            .iter()
+            .flat_map(|assembly| assembly.point_dest_vars.0.values().copied())
+            .collect_vec();
+        let mut contents = vec![];
+        self.sat
+            .write_dimacs(&mut contents, &projection)
+            .and_then(|()| fs::write(path, contents))
+            .map_err(|err| format!("could not write {path:?}: {err}"))?;
+        println!("wrote search to {path}");
+        Ok(())
+    }
+    /// write the search to an OPB file for pseudo-boolean solvers, with the objective to optimize
+    fn write_opb(&self, path: &str, objective: Option<Objective>) -> Result<(), String> {
+        let objective = objective.map(|objective| self.objective_vars(objective));
+        let objective = objective.as_ref().map(|(goal, vars)| (*goal, &vars[..]));
+        let mut contents = vec![];
+        self.sat
+            .write_opb(&mut contents, objective)
+            .and_then(|()| fs::write(path, contents))
+            .map_err(|err| format!("could not write {path:?}: {err}"))?;
+        println!("wrote search to {path}");
+        Ok(())
+    }
+    /// find a solution that is best for the objective, and only allow equally good solutions after it
+    fn optimize(&mut self, objective: Objective) -> Result<Option<SatSolution>, String> {
+        let name = match objective {
+            Objective::Displacement => "displacement",
+            Objective::Rotations => "turned pieces",
+            Objective::FixedPoints => "pieces left in place",
+        };
+        let (goal, weighted_vars) = self.objective_vars(objective);
+        let Some((solution, weight)) = self.sat.optimize(goal, &weighted_vars)? else {
+            return Ok(None);
+        };
+        println!("best {name}: {weight}");
+        Ok(Some(solution))
+    }
+    /// placements weighted by how much they count towards the objective
+    fn objective_vars(&self, objective: Objective) -> (Goal, Vec<(usize, Lit)>) {
+        let goal = match objective {
+            Objective::Displacement | Objective::Rotations => Goal::Maximize,
+            Objective::FixedPoints => Goal::Minimize,
+        };
+        let weighted_vars = self
+            .assemblies
+            .iter()
+            .flat_map(|assembly| &assembly.placements)
+            .map(|placement| (self.placement_weight(objective, placement), placement.var))
+            .filter(|&(weight, _)| weight > 0)
+            .collect_vec();
+        (goal, weighted_vars)
+    }
+    /// how much a placement counts towards the objective.
+    /// displacement is measured between piece centers, rounded to whole cells
+    fn placement_weight(&self, objective: Objective, placement: &Placement) -> usize {
+        let src_piece = self.puzzle.point_piece(placement.src_point);
+        let dest_piece = self.dest.point_piece(placement.dest_point);
+        match objective {
+            Objective::Displacement => {
+                let moved = centroid(&self.dest.piece_corners(dest_piece))
+                    - centroid(&self.puzzle.piece_corners(src_piece));
+                (moved.x.abs() + moved.y.abs()).round() as usize
+            }
+            Objective::Rotations => {
+                (placement.flipped || self.turn_degrees(placement) != 0) as usize
+            }
+            Objective::FixedPoints => self.same_place(src_piece, dest_piece) as usize,
+        }
+    }
+    /// how far a placement turns its piece clockwise, in whole degrees from 0 to 359
+    fn turn_degrees(&self, placement: &Placement) -> u32 {
+        let (src_start, src_end) = self.puzzle.point_side(placement.src_point);
+        let (dest_start, dest_end) = self.dest.point_side(placement.dest_point);
+        let (src_side, dest_side) = (src_end - src_start, dest_end - dest_start);
+        let degrees = src_side
+            .cross(dest_side)
+            .atan2(src_side.dot(dest_side))
+            .to_degrees()
+            .round();
+        (degrees as i32).rem_euclid(360) as u32
+    }
+    /// whether a destination piece is where a source piece started
+    fn same_place(&self, src_piece: PieceKey, dest_piece: PieceKey) -> bool {
+        let moved = centroid(&self.dest.piece_corners(dest_piece))
+            - centroid(&self.puzzle.piece_corners(src_piece));
+        moved.length() < 1e-6
+    }
+    /// search with more and more unmatched edges until there is a solution,
+    /// returning the search with the fewest and its first solution
+    fn fewest_unmatched(
+        puzzle: P,
+        dest: P,
+        options: &Options,
+        start_time: Instant,
+    ) -> Result<(Self, SatSolution), String> {
+        let max_unmatched = options
+            .unmatched_edges
+            .as_ref()
+            .map_or(puzzle.num_edges(), |edges| edges.len());
+        // every matched edge needs another, so the parity of unmatched edges is fixed
+        for unmatched in (puzzle.num_edges() % 2..=max_unmatched).step_by(2) {
+            let mut s = Self::configure(puzzle.clone(), dest.clone(), options, Some(unmatched))?;
+            if let Some(solution) = s.sat.try_solve()? {
+                println!("fewest unmatched edges: {unmatched}");
+                return Ok((s, solution));
+            }
+            println!(
+                "no solutions with {} unmatched edges ({} total)",
+                unmatched,
+                humantime::format_duration(Instant::now() - start_time)
+            );
+        }
+        Err("no solutions with any number of unmatched edges".to_owned())
+    }
+    /// set up the search described by the options, with `unmatched` instead of `options.unmatched`
+    fn configure(
+        puzzle: P,
+        dest: P,
+        options: &Options,
+        unmatched: Option<usize>,
+    ) -> Result<Self, String> {
+        let mut s = Self::new(puzzle, dest, options.assemblies - 1);
+        s.configure_solver(options)?;
+        if options.dimacs.is_some() || options.opb.is_some() {
+            s.sat.record_clauses();
+        }
+        s.flip = options.flip;
+        s.tabs = options.tabs || !options.tab_counts.is_empty() || options.alternate_tabs;
+        s.tab_counts = options.tab_counts.clone();
+        s.alternate_tabs = options.alternate_tabs;
+        if options.assemblies == 2 {
+            if let Some(max_unmatched) = options.max_unmatched {
+                s.unmatched = max_unmatched;
+                s.unmatched_at_most = true;
+            } else {
+                s.unmatched = unmatched.unwrap_or(s.puzzle.num_edges() % 2);
+            }
+        } else if unmatched
+            .or(options.max_unmatched)
+            .is_some_and(|unmatched| unmatched > 0)
+            || options.fewest_unmatched
+        {
+            return Err("unmatched edges are only supported with two assemblies".to_owned());
+        } else if options.cut.is_some() {
+            return Err("cuts are only supported with two assemblies".to_owned());
+        }
+        if let Some(names) = &options.unmatched_edges {
+            let edges = names
+                .iter()
+                .map(|name| {
+                    puzzle_find_edge(&s.puzzle, name)
+                        .ok_or_else(|| format!("unknown edge {name:?}"))
+                })
+                .collect::<Result<_, _>>()?;
+            s.unmatched_edges = Some(edges);
+        }
+        if options.assemblies == 2
+            && !s.unmatched_at_most
+            && !(s.puzzle.num_edges() + s.unmatched).is_multiple_of(2)
+        {
+            return Err(format!(
+                "cannot pair up {} edges with {} unmatched",
+                s.puzzle.num_edges(),
+                s.unmatched
+            ));
+        }
+        if s.unmatched_edges.is_some() && s.unmatched == 0 && !options.fewest_unmatched {
+            return Err(
+                "--unmatched-edges needs --unmatched or --max-unmatched above 0, \
+                 --fewest-unmatched, or an odd number of edges"
+                    .to_owned(),
+            );
+        }
+        if options.assemblies > 2 && !s.puzzle.num_edges().is_multiple_of(options.assemblies) {
+            return Err(format!(
+                "cannot split {} edges into groups of {} with the same shape",
+                s.puzzle.num_edges(),
+                options.assemblies
+            ));
+        }
 
         s.add_point_dest_vars();
         s.add_one_hot_point_dest();
@@ -75,11 +609,196 @@ impl<P: Puzzle> JigsawDoubler<P> {
         s.add_point_dest_adjacent_not_same();
         s.add_edge_matching_vars();
         s.add_one_hot_edge_matching();
+        if s.assemblies.len() > 1 {
+            s.add_point_dest_adjacent_at_most_once();
+            s.add_same_shape_vars();
+        }
+        if s.tabs {
+            s.add_tab_vars();
+            s.add_tab_rules();
+        }
+        s.add_placement_options(options)?;
+        if options.turns_matter() {
+            s.add_board_symmetries();
+        }
+        s.name_vars();
+        Ok(s)
+    }
+    /// cycle, symmetry and placement rules from the options
+    fn add_placement_options(&mut self, options: &Options) -> Result<(), String> {
+        self.min_cycle = options.min_cycle.unwrap_or(1);
+        self.max_cycle = options.max_cycle;
+        self.single_cycle = options.single_cycle;
+        if self.min_cycle > 1 || self.max_cycle.is_some() || self.single_cycle {
+            self.add_cycle_rules()?;
+        }
+        if let Some(symmetry) = options.symmetry {
+            self.add_symmetry(symmetry)?;
+        }
+        for text in &options.constraints {
+            let rules = PlacementRule::parse_lines(&self.puzzle, &self.dest, text)?;
+            self.add_placement_rules(&rules)?;
+        }
+        if let Some((labels, target_labels)) = &options.labels {
+            let labels = PieceLabels::parse(&self.puzzle, labels)?;
+            let target_labels = PieceLabels::parse(&self.dest, target_labels)?;
+            self.add_label_rules(&labels, &target_labels)?;
+        }
+        Ok(())
+    }
+    /// find every assembly where all seams fit, to check a puzzle cut with these shapes only has the intended two
+    pub fn check(
+        puzzle: P,
+        options: &Options,
+        text: &str,
+        start_time: Instant,
+    ) -> Result<(), String> {
+        if options.assemblies != 2 {
+            return Err("checking is only supported with two assemblies".to_owned());
+        }
+        let shapes = EdgeShapes::parse(&puzzle, text)?;
+        let mut s = Self::fitting(puzzle, options, |puzzle, a, b| shapes.fits(puzzle, a, b))?;
+        s.add_board_symmetries();
+        s.add_first_piece_symmetry();
+        let original = puzzle_points(&s.puzzle)
+            .map(|point| (point, point))
+            .collect_vec();
+
+        println!("constraints configured, starting solve");
+        let mut last = start_time;
+        let mut count = 0usize;
+        let mut unintended = 0;
+        while let Some(solution) = s.sat.try_solve()? {
+            let now = Instant::now();
+            count += 1;
+            let assembly = s.decode(&solution, 0);
+            let kind = if s.is_copy_of(&assembly, &original) {
+                "original"
+            } else if shapes
+                .intended
+                .as_ref()
+                .is_some_and(|intended| s.is_copy_of(&assembly, intended))
+            {
+                "intended"
+            } else {
+                unintended += 1;
+                "unintended"
+            };
+            println!(
+                "found {} assembly {} in {} ({} total)",
+                kind,
+                count,
+                humantime::format_duration(now - last),
+                humantime::format_duration(now - start_time)
+            );
+            s.print_point_dest(&solution, 0);
+            if s.flip {
+                s.print_flipped_pieces(&solution, 0);
+            }
+            println!();
+            s.add_prior_solution(&solution);
+            last = now;
+        }
+
+        let now = Instant::now();
+        println!(
+            "no more assemblies. found {} assemblies in {}",
+            count,
+            humantime::format_duration(now - start_time)
+        );
+        if shapes.intended.is_none() {
+            // anything other than the original might be the intended one
+            unintended = count.saturating_sub(2);
+        }
+        if count == 2 && unintended == 0 {
+            println!("the cut has exactly two assemblies");
+        } else {
+            println!("the cut has {unintended} unintended assemblies");
+        }
+        Ok(())
+    }
+    /// search for another assembly of a doubled puzzle that has already been cut,
+    /// where every seam fits but the pieces are put together differently than both known assemblies
+    pub fn extend(
+        puzzle: P,
+        options: &Options,
+        text: &str,
+        start_time: Instant,
+    ) -> Result<(), String> {
+        if options.assemblies != 2 {
+            return Err("extending is only supported from two assemblies".to_owned());
+        }
+        let shapes = EdgeShapes::parse(&puzzle, text)?;
+        let intended = shapes
+            .intended
+            .clone()
+            .ok_or("extending needs the piece dest line of the solution")?;
+        let mut s = Self::fitting(puzzle, options, |puzzle, a, b| shapes.fits(puzzle, a, b))?;
+        s.known_assemblies = 2;
+        s.add_edge_matching_vars();
+        s.add_board_symmetries();
+        s.add_first_piece_symmetry();
+        let original = puzzle_pieces(&s.puzzle)
+            .map(|piece| s.puzzle.arbitrary_point_on_piece(piece))
+            .map(|point| (point, point))
+            .collect_vec();
+        s.add_known_assembly(&original);
+        s.add_known_assembly(&intended);
 
         println!("constraints configured, starting solve");
         let mut last = start_time;
         let mut count = 0;
-        while let Some(solution) = s.sat.solve() {
+        while let Some(solution) = s.sat.try_solve()? {
+            let now = Instant::now();
+            count += 1;
+            println!(
+                "found assembly {} in {} ({} total)",
+                count,
+                humantime::format_duration(now - last),
+                humantime::format_duration(now - start_time)
+            );
+            s.print_point_dest(&solution, 0);
+            if s.flip {
+                s.print_flipped_pieces(&solution, 0);
+            }
+            s.print_edge_matching(&solution, 0);
+            println!();
+            s.add_prior_solution(&solution);
+            last = now;
+        }
+
+        let now = Instant::now();
+        println!(
+            "no more assemblies. found {} assemblies in {}",
+            count,
+            humantime::format_duration(now - start_time)
+        );
+        if count == 0 {
+            println!("the cut has no assembly other than the two known ones");
+        }
+        Ok(())
+    }
+    /// solve an edge matching puzzle, finding every way to put its pieces on the board
+    /// so that the sides at every seam have the same colour
+    pub fn match_colors(
+        puzzle: P,
+        options: &Options,
+        text: &str,
+        start_time: Instant,
+    ) -> Result<(), String> {
+        let colors = EdgeColors::parse(&puzzle, text)?;
+        let mut s = Self::fitting(puzzle, options, |_, a, b| colors.fits(a, b))?;
+        s.add_board_symmetries();
+        s.add_placement_options(options)?;
+        // rules about where pieces go may only hold for some turned copies of a solution
+        if !options.turns_matter() {
+            s.add_first_piece_symmetry();
+        }
+
+        println!("constraints configured, starting solve");
+        let mut last = start_time;
+        let mut count = 0;
+        while let Some(solution) = s.sat.try_solve()? {
             let now = Instant::now();
             count += 1;
             println!(
@@ -88,8 +807,10 @@ impl<P: Puzzle> JigsawDoubler<P> {
                 humantime::format_duration(now - last),
                 humantime::format_duration(now - start_time)
             );
-            s.print_point_dest(&solution);
-            s.print_edge_matching(&solution);
+            s.print_point_dest(&solution, 0);
+            if s.flip {
+                s.print_flipped_pieces(&solution, 0);
+            }
             println!();
             s.add_prior_solution(&solution);
             last = now;
@@ -100,141 +821,804 @@ impl<P: Puzzle> JigsawDoubler<P> {
             "no more solutions. found {} solutions in {}",
             count,
             humantime::format_duration(now - start_time)
-        )
+        );
+        Ok(())
     }
-    fn new(puzzle: P) -> Self {
+    /// whether the assembly puts the source points where given, or where one of the symmetries moves them
+    fn is_copy_of(&self, assembly: &DoubledSolution, point_dests: &[(PointKey, PointKey)]) -> bool {
+        let identity = puzzle_points(&self.dest).collect_vec();
+        std::iter::once(&identity)
+            .chain(self.symmetries.iter().map(|(map, _)| map))
+            .any(|map| {
+                point_dests
+                    .iter()
+                    .all(|&(src, dest)| assembly.point_dest(src) == map[dest.0])
+            })
+    }
+    /// search for any assembly where every seam fits
+    fn fitting(
+        puzzle: P,
+        options: &Options,
+        fits: impl Fn(&P, PointKey, PointKey) -> bool,
+    ) -> Result<Self, String> {
+        let mut s = Self::new(puzzle.clone(), puzzle, 1);
+        s.configure_solver(options)?;
+        s.flip = options.flip;
+
+        s.add_point_dest_vars();
+        s.add_one_hot_point_dest();
+        s.add_one_hot_point_src();
+        s.add_point_dest_adjacent_vars();
+        s.add_point_dest_adjacent_fits(fits);
+        Ok(s)
+    }
+    /// rule out the turned copies of each solution, and mirrored copies if pieces can be turned over
+    fn add_board_symmetries(&mut self) {
+        self.symmetries = board_symmetries(&self.dest)
+            .into_iter()
+            .filter(|&(_, mirrors)| self.flip || !mirrors)
+            .collect();
+    }
+    /// only put the first piece in the first of the places the symmetries move into each other,
+    /// as every solution has a copy with it there.
+    /// the copies that keep it there are ruled out by `add_prior_solution`
+    fn add_first_piece_symmetry(&mut self) {
+        let piece = PieceKey(0);
+        for dest_piece in puzzle_pieces(&self.dest) {
+            let point = self.dest.arbitrary_point_on_piece(dest_piece);
+            let has_earlier_copy = self
+                .symmetries
+                .iter()
+                .any(|(map, _)| self.dest.point_piece(map[point.0]) < dest_piece);
+            if has_earlier_copy {
+                let vars = self.place_vars(0, Place::Piece(piece), Place::Piece(dest_piece));
+                self.sat.nor_clause(&vars);
+            }
+        }
+    }
+    /// use the solver command and count encoding from the options, before adding any vars
+    fn configure_solver(&mut self, options: &Options) -> Result<(), String> {
+        if let Some(command) = &options.solver_cmd {
+            self.sat = SatProblem::with_backend(Box::new(ExternalSolver::new(command)?));
+        }
+        self.sat.set_count_encoding(options.count_encoding);
+        Ok(())
+    }
+    /// `assemblies` is the number of assemblies other than the original
+    fn new(puzzle: P, dest: P, assemblies: usize) -> Self {
+        let homes = puzzle_pieces(&dest)
+            .map(|dest_piece| {
+                let center = centroid(&dest.piece_corners(dest_piece));
+                puzzle_pieces(&puzzle).find(|&src_piece| {
+                    (centroid(&puzzle.piece_corners(src_piece)) - center).length() < 1e-6
+                })
+            })
+            .collect();
         Self {
             puzzle,
+            dest,
             sat: SatProblem::new(),
-            point_dest_vars: TableVars::new(),
-            point_dest_adjacent_vars: MatchingVars::new(),
-            edge_matching_vars: MatchingVars::new(),
+            flip: false,
+            assemblies: (0..assemblies).map(|_| Assembly::new()).collect(),
+            tabs: false,
+            tab_vars: vec![],
+            tab_counts: vec![],
+            alternate_tabs: false,
+            same_shape_vars: MatchingVars::new(),
+            unmatched: 0,
+            unmatched_at_most: false,
+            unmatched_edges: None,
+            homes,
+            min_cycle: 1,
+            max_cycle: None,
+            single_cycle: false,
+            known_assemblies: 1,
+            symmetries: vec![],
         }
     }
 
     fn add_point_dest_vars(&mut self) {
-        // create var for each src_piece dest_point pair
-        for src_piece in puzzle_pieces(&self.puzzle) {
-            let src_point = self.puzzle.arbitrary_point_on_piece(src_piece);
-            for dest_point in puzzle_exchange_points(&self.puzzle, src_point) {
-                let var = self.sat.var();
-
-                // and write to all implied src_point dest_point pairs
-                let mut src_point_other = src_point;
-                let mut dest_point_other = dest_point;
+        for assembly in &mut self.assemblies {
+            // create var for each src_piece dest_point pair, and for each flipped pair
+            for src_piece in puzzle_pieces(&self.puzzle) {
+                let src_point = self.puzzle.arbitrary_point_on_piece(src_piece);
+                let dest_points = puzzle_points(&self.dest)
+                    .filter(|&dest_point| {
+                        self.puzzle
+                            .can_exchange_into(src_point, &self.dest, dest_point, false)
+                    })
+                    .map(|dest_point| (dest_point, false))
+                    .collect_vec();
+                let flipped_dest_points = puzzle_points(&self.dest)
+                    .filter(|&dest_point| {
+                        self.flip
+                            && self
+                                .puzzle
+                                .can_exchange_into(src_point, &self.dest, dest_point, true)
+                    })
+                    .map(|dest_point| (dest_point, true))
+                    .collect_vec();
+                for (dest_point, flipped) in dest_points.into_iter().chain(flipped_dest_points) {
+                    assembly.placements.push(Placement {
+                        src_point,
+                        dest_point,
+                        flipped,
+                        var: self.sat.var(),
+                    });
+                }
+            }
+
+            // write to all implied src_point dest_point pairs
+            let mut lands_vars = BTreeMap::<_, Vec<_>>::new();
+            for placement in &assembly.placements {
+                let mut src_point_other = placement.src_point;
+                let mut dest_point_other = placement.dest_point;
                 loop {
-                    self.point_dest_vars
-                        .put(src_point_other, dest_point_other, var);
+                    lands_vars
+                        .entry((src_point_other, dest_point_other))
+                        .or_default()
+                        .push(placement.var);
                     src_point_other = self.puzzle.next_point_on_piece(src_point_other);
-                    dest_point_other = self.puzzle.next_point_on_piece(dest_point_other);
-                    if src_point_other == src_point {
-                        debug_assert_eq!(dest_point_other, dest_point);
+                    dest_point_other = if placement.flipped {
+                        self.dest.previous_point_on_piece(dest_point_other)
+                    } else {
+                        self.dest.next_point_on_piece(dest_point_other)
+                    };
+                    if src_point_other == placement.src_point {
+                        debug_assert_eq!(dest_point_other, placement.dest_point);
                         break;
                     }
                 }
             }
+            for ((src_point, dest_point), vars) in lands_vars {
+                let var = match vars[..] {
+                    [var] => var,
+                    _ => self.sat.or_var(&vars),
+                };
+                assembly.point_dest_vars.put(src_point, dest_point, var);
+            }
         }
     }
     fn add_one_hot_point_dest(&mut self) {
-        for src_piece in puzzle_pieces(&self.puzzle) {
-            let src_point = self.puzzle.arbitrary_point_on_piece(src_piece);
-            let dest_vars = puzzle_points(&self.puzzle)
-                .filter_map(|dest_point| self.point_dest_vars.get(src_point, dest_point))
-                .collect_vec();
-            self.sat.exact_count_clause(1, &dest_vars);
+        for assembly in &self.assemblies {
+            for src_piece in puzzle_pieces(&self.puzzle) {
+                let src_point = self.puzzle.arbitrary_point_on_piece(src_piece);
+                let placement_vars = assembly
+                    .placements
+                    .iter()
+                    .filter(|placement| placement.src_point == src_point)
+                    .map(|placement| placement.var)
+                    .collect_vec();
+                self.sat.exact_count_clause(1, &placement_vars);
+            }
         }
     }
     fn add_one_hot_point_src(&mut self) {
-        for dest_piece in puzzle_pieces(&self.puzzle) {
-            let dest_point = self.puzzle.arbitrary_point_on_piece(dest_piece);
-            let src_vars = puzzle_points(&self.puzzle)
-                .filter_map(|src_point| self.point_dest_vars.get(src_point, dest_point))
-                .collect_vec();
-            self.sat.exact_count_clause(1, &src_vars);
+        for assembly in &self.assemblies {
+            for dest_piece in puzzle_pieces(&self.dest) {
+                let dest_point = self.dest.arbitrary_point_on_piece(dest_piece);
+                let src_vars = puzzle_points(&self.puzzle)
+                    .filter_map(|src_point| assembly.point_dest_vars.get(src_point, dest_point))
+                    .collect_vec();
+                self.sat.exact_count_clause(1, &src_vars);
+            }
         }
     }
 
     fn add_point_dest_adjacent_vars(&mut self) {
+        for assembly in &mut self.assemblies {
+            for (src_point_a, src_point_b) in puzzle_point_pairs(&self.puzzle) {
+                let dest_adj_vars = puzzle_points(&self.dest)
+                    .filter_map(|dest_point_a| {
+                        let var_a = assembly.point_dest_vars.get(src_point_a, dest_point_a)?;
+                        let dest_point_b = self.dest.other_point_on_edge(dest_point_a);
+                        let var_b = assembly.point_dest_vars.get(src_point_b, dest_point_b)?;
+                        Some(self.sat.and_var(&[var_a, var_b]))
+                    })
+                    .collect_vec();
+                let dest_adj_var = self.sat.or_var(&dest_adj_vars);
+                assembly
+                    .point_dest_adjacent_vars
+                    .put(src_point_a, src_point_b, dest_adj_var);
+            }
+        }
+    }
+    fn add_point_dest_adjacent_not_same(&mut self) {
+        for assembly in 0..self.assemblies.len() {
+            let mut stays_adjacent_vars = vec![];
+            for edge in puzzle_edges(&self.puzzle) {
+                let stays_adjacent_var = self.stays_adjacent_var(assembly, edge);
+                if self.may_be_unmatched(edge) {
+                    stays_adjacent_vars.push(stays_adjacent_var);
+                } else {
+                    self.sat.not_clause(stays_adjacent_var);
+                }
+            }
+            let min_unmatched = if self.unmatched_at_most {
+                0
+            } else {
+                self.unmatched
+            };
+            self.sat
+                .count_between_clause(min_unmatched, self.unmatched, &stays_adjacent_vars);
+        }
+    }
+    fn stays_adjacent_var(&self, assembly: usize, edge: EdgeKey) -> Lit {
+        let point_1 = self.puzzle.arbitrary_point_on_edge(edge);
+        let point_2 = self.puzzle.other_point_on_edge(point_1);
+        self.assemblies[assembly]
+            .point_dest_adjacent_vars
+            .get(point_1, point_2)
+            .unwrap()
+    }
+    fn may_be_unmatched(&self, edge: EdgeKey) -> bool {
+        self.unmatched > 0
+            && self
+                .unmatched_edges
+                .as_ref()
+                .is_none_or(|edges| edges.contains(&edge))
+    }
+    /// no two points are adjacent in more than one assembly
+    fn add_point_dest_adjacent_at_most_once(&mut self) {
         for (src_point_a, src_point_b) in puzzle_point_pairs(&self.puzzle) {
-            let dest_adj_vars = puzzle_points(&self.puzzle)
-                .filter_map(|dest_point_a| {
-                    let var_a = self.point_dest_vars.get(src_point_a, dest_point_a)?;
-                    let dest_point_b = self.puzzle.other_point_on_edge(dest_point_a);
-                    let var_b = self.point_dest_vars.get(src_point_b, dest_point_b)?;
-                    Some(self.sat.and_var(&[var_a, var_b]))
+            let dest_adj_vars = self
+                .assemblies
+                .iter()
+                .map(|assembly| {
+                    assembly
+                        .point_dest_adjacent_vars
+                        .get(src_point_a, src_point_b)
+                        .unwrap()
                 })
                 .collect_vec();
-            let dest_adj_var = self.sat.or_var(&dest_adj_vars);
-            self.point_dest_adjacent_vars
-                .put(src_point_a, src_point_b, dest_adj_var);
+            for (&var_a, &var_b) in dest_adj_vars.iter().tuple_combinations() {
+                self.sat.nand_clause(&[var_a, var_b]);
+            }
         }
     }
-    fn add_point_dest_adjacent_not_same(&mut self) {
-        for edge in puzzle_edges(&self.puzzle) {
-            let point_1 = self.puzzle.arbitrary_point_on_edge(edge);
-            let point_2 = self.puzzle.other_point_on_edge(point_1);
-            let stays_adjacent_var = self.point_dest_adjacent_vars.get(point_1, point_2).unwrap();
-            self.sat.not_clause(stays_adjacent_var);
+
+    fn add_point_dest_adjacent_fits(&mut self, fits: impl Fn(&P, PointKey, PointKey) -> bool) {
+        let assembly = &self.assemblies[0];
+        for (src_point_a, src_point_b) in puzzle_point_pairs(&self.puzzle) {
+            if !fits(&self.puzzle, src_point_a, src_point_b) {
+                let dest_adj_var = assembly
+                    .point_dest_adjacent_vars
+                    .get(src_point_a, src_point_b)
+                    .unwrap();
+                self.sat.not_clause(dest_adj_var);
+            }
+        }
+    }
+
+    /// rule out an assembly given by where the `arbitrary_point_on_piece` of each source piece lands,
+    /// and its copies moved by the symmetries
+    fn add_known_assembly(&mut self, point_dests: &[(PointKey, PointKey)]) {
+        let assembly = &self.assemblies[0];
+        let identity = puzzle_points(&self.dest).collect_vec();
+        let copies = std::iter::once(&identity)
+            .chain(self.symmetries.iter().map(|(map, _)| map))
+            .filter_map(|map| {
+                point_dests
+                    .iter()
+                    .map(|&(src_point, dest_point)| {
+                        assembly.point_dest_vars.get(src_point, map[dest_point.0])
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect_vec();
+        // an assembly that doesn't fit the shapes is already ruled out
+        for lands_vars in copies {
+            self.sat.nand_clause(&lands_vars);
         }
     }
 
     fn add_edge_matching_vars(&mut self) {
+        for assembly in &mut self.assemblies {
+            for (edge_a, edge_b) in puzzle_edge_pairs(&self.puzzle) {
+                let point_a1 = self.puzzle.arbitrary_point_on_edge(edge_a);
+                let point_a2 = self.puzzle.other_point_on_edge(point_a1);
+                let point_b1 = self.puzzle.arbitrary_point_on_edge(edge_b);
+                let point_b2 = self.puzzle.other_point_on_edge(point_b1);
+                let adjacent_vars = &assembly.point_dest_adjacent_vars;
+                let matching_vars = [
+                    adjacent_vars.get(point_a1, point_b1),
+                    adjacent_vars.get(point_a1, point_b2),
+                    adjacent_vars.get(point_a2, point_b1),
+                    adjacent_vars.get(point_a2, point_b2),
+                ]
+                .into_iter()
+                .flatten()
+                .collect_vec();
+                if !matching_vars.is_empty() {
+                    let matching_var = self.sat.or_var(&matching_vars);
+                    assembly
+                        .edge_matching_vars
+                        .put(edge_a, edge_b, matching_var);
+                }
+            }
+        }
+    }
+    fn add_one_hot_edge_matching(&mut self) {
+        for assembly in 0..self.assemblies.len() {
+            for edge_a in puzzle_edges(&self.puzzle) {
+                let mut matching_vars = puzzle_edges(&self.puzzle)
+                    .filter_map(|edge_b| {
+                        self.assemblies[assembly]
+                            .edge_matching_vars
+                            .get(edge_a, edge_b)
+                    })
+                    .collect_vec();
+                if self.may_be_unmatched(edge_a) {
+                    // an edge that stays together only matches itself
+                    matching_vars.push(self.stays_adjacent_var(assembly, edge_a));
+                }
+                self.sat.exact_count_clause(1, &matching_vars);
+            }
+        }
+    }
+
+    /// with two assemblies the shapes of matched edges are always consistent,
+    /// but with more each edge must have the same shape as the edges it matches in every assembly,
+    /// so every shape is shared by exactly as many edges as there are assemblies
+    fn add_same_shape_vars(&mut self) {
+        // the shape of a point's side is the reverse of the other side of its edge,
+        // so one var covers both pairings of the points of two edges
         for (edge_a, edge_b) in puzzle_edge_pairs(&self.puzzle) {
             let point_a1 = self.puzzle.arbitrary_point_on_edge(edge_a);
             let point_a2 = self.puzzle.other_point_on_edge(point_a1);
             let point_b1 = self.puzzle.arbitrary_point_on_edge(edge_b);
             let point_b2 = self.puzzle.other_point_on_edge(point_b1);
-            let matching_vars = [
-                self.point_dest_adjacent_vars.get(point_a1, point_b1),
-                self.point_dest_adjacent_vars.get(point_a1, point_b2),
-                self.point_dest_adjacent_vars.get(point_a2, point_b1),
-                self.point_dest_adjacent_vars.get(point_a2, point_b2),
-            ]
-            .into_iter()
-            .filter_map(|v| v)
+            let same_var = self.sat.var();
+            let reversed_var = self.sat.var();
+            self.same_shape_vars.put(point_a1, point_b1, same_var);
+            self.same_shape_vars.put(point_a2, point_b2, same_var);
+            self.same_shape_vars.put(point_a1, point_b2, reversed_var);
+            self.same_shape_vars.put(point_a2, point_b1, reversed_var);
+            self.sat.nand_clause(&[same_var, reversed_var]);
+        }
+
+        // transitive. moving all three points to the other sides of their edges gives the same vars,
+        // so the point on the first edge is always its arbitrary one
+        let edges = puzzle_edges(&self.puzzle).collect_vec();
+        for (edge_a, edge_b, edge_c) in edges.into_iter().tuple_combinations() {
+            let a = self.puzzle.arbitrary_point_on_edge(edge_a);
+            let b = self.puzzle.arbitrary_point_on_edge(edge_b);
+            let c = self.puzzle.arbitrary_point_on_edge(edge_c);
+            for b in [b, self.puzzle.other_point_on_edge(b)] {
+                for c in [c, self.puzzle.other_point_on_edge(c)] {
+                    let ab = self.same_shape_vars.get(a, b).unwrap();
+                    let bc = self.same_shape_vars.get(b, c).unwrap();
+                    let ac = self.same_shape_vars.get(a, c).unwrap();
+                    self.sat.or_clause(&[!ab, !bc, ac]);
+                    self.sat.or_clause(&[!ab, !ac, bc]);
+                    self.sat.or_clause(&[!ac, !bc, ab]);
+                }
+            }
+        }
+
+        // a point ends against the other side of an edge with the same shape
+        for assembly in &self.assemblies {
+            for (src_point_a, src_point_b) in puzzle_point_pairs(&self.puzzle) {
+                if self.puzzle.point_edge(src_point_a) == self.puzzle.point_edge(src_point_b) {
+                    continue;
+                }
+                let dest_adj_var = assembly
+                    .point_dest_adjacent_vars
+                    .get(src_point_a, src_point_b)
+                    .unwrap();
+                let same_var = self
+                    .same_shape_vars
+                    .get(src_point_a, self.puzzle.other_point_on_edge(src_point_b))
+                    .unwrap();
+                self.sat.implies_clause(dest_adj_var, same_var);
+            }
+        }
+
+        // one edge with the same shape for each other assembly
+        for edge_a in puzzle_edges(&self.puzzle) {
+            let point_a = self.puzzle.arbitrary_point_on_edge(edge_a);
+            let same_vars = puzzle_points(&self.puzzle)
+                .filter_map(|point_b| self.same_shape_vars.get(point_a, point_b))
+                .collect_vec();
+            self.sat
+                .exact_count_clause(self.assemblies.len(), &same_vars);
+        }
+    }
+
+    /// rules about where pieces go apply to every assembly other than the original
+    fn add_placement_rules(&mut self, rules: &[PlacementRule]) -> Result<(), String> {
+        for &rule in rules {
+            for clause in self.placement_rule_clauses(rule)? {
+                self.sat.or_clause(&clause);
+            }
+        }
+        Ok(())
+    }
+    /// clauses that make a placement rule hold in every assembly other than the original
+    fn placement_rule_clauses(&self, rule: PlacementRule) -> Result<Vec<Vec<Lit>>, String> {
+        let nor = |vars: Vec<Lit>| vars.into_iter().map(|var| vec![!var]).collect_vec();
+        let mut clauses = vec![];
+        for assembly in 0..self.assemblies.len() {
+            match rule {
+                PlacementRule::Goes { src, dest } => {
+                    let vars = self.place_vars(assembly, src, dest);
+                    if vars.is_empty() {
+                        return Err(format!(
+                            "{} can't be put in place of {}",
+                            self.format_place(&self.puzzle, src),
+                            self.format_place(&self.dest, dest)
+                        ));
+                    }
+                    clauses.push(vars);
+                }
+                PlacementRule::DoesNotGo { src, dest } => {
+                    clauses.extend(nor(self.place_vars(assembly, src, dest)));
+                }
+                PlacementRule::Moves(piece) => {
+                    for src_piece in self.rule_pieces(piece) {
+                        let src_point = self.puzzle.arbitrary_point_on_piece(src_piece);
+                        let (stay_vars, move_vars): (Vec<_>, Vec<_>) = puzzle_points(&self.dest)
+                            .filter_map(|dest_point| {
+                                let var = self.assemblies[assembly]
+                                    .point_dest_vars
+                                    .get(src_point, dest_point)?;
+                                let dest_piece = self.dest.point_piece(dest_point);
+                                Some((self.same_place(src_piece, dest_piece), var))
+                            })
+                            .partition(|&(stays, _)| stays);
+                        if move_vars.is_empty() {
+                            return Err(format!(
+                                "piece {} can't move",
+                                self.puzzle.format_piece(src_piece)
+                            ));
+                        }
+                        let stay_vars = stay_vars.into_iter().map(|(_, var)| var).collect_vec();
+                        clauses.extend(nor(stay_vars));
+                    }
+                }
+                PlacementRule::Turns { piece, degrees } => {
+                    let turns = |placement: &Placement| match degrees {
+                        None => placement.flipped || self.turn_degrees(placement) != 0,
+                        Some(degrees) => {
+                            !placement.flipped && self.turn_degrees(placement) == degrees
+                        }
+                    };
+                    for src_piece in self.rule_pieces(piece) {
+                        let (turned, unturned): (Vec<_>, Vec<_>) = self.assemblies[assembly]
+                            .placements
+                            .iter()
+                            .filter(|placement| {
+                                self.puzzle.point_piece(placement.src_point) == src_piece
+                            })
+                            .partition(|placement| turns(placement));
+                        if turned.is_empty() {
+                            let name = self.puzzle.format_piece(src_piece);
+                            return Err(match degrees {
+                                None => format!("piece {name} can't turn"),
+                                Some(degrees) => {
+                                    format!("piece {name} can't turn {degrees} degrees")
+                                }
+                            });
+                        }
+                        clauses.extend(nor(unturned
+                            .into_iter()
+                            .map(|placement| placement.var)
+                            .collect()));
+                    }
+                }
+            }
+        }
+        Ok(clauses)
+    }
+    /// limit the lengths of the cycles pieces move in, as a permutation of the places they start in
+    fn add_cycle_rules(&mut self) -> Result<(), String> {
+        let homes = self
+            .homes
+            .clone()
+            .ok_or("cycle rules need a destination board with pieces in the same places")?;
+        let mut dest_pieces = vec![PieceKey(0); homes.len()];
+        for (dest_piece, src_piece) in puzzle_pieces(&self.dest).zip(&homes) {
+            dest_pieces[src_piece.0] = dest_piece;
+        }
+        let num_pieces = self.puzzle.num_pieces();
+        let powers_needed = (self.min_cycle - 1).max(self.max_cycle.unwrap_or(0));
+        for assembly in 0..self.assemblies.len() {
+            // whether piece `a` goes in the place where piece `b` started, `None` if it can't
+            let moves = puzzle_pieces(&self.puzzle)
+                .map(|piece_a| {
+                    puzzle_pieces(&self.puzzle)
+                        .map(|piece_b| {
+                            let vars = self.place_vars(
+                                assembly,
+                                Place::Piece(piece_a),
+                                Place::Piece(dest_pieces[piece_b.0]),
+                            );
+                            match vars[..] {
+                                [] => None,
+                                [var] => Some(var),
+                                _ => Some(self.sat.or_var(&vars)),
+                            }
+                        })
+                        .collect_vec()
+                })
+                .collect_vec();
+            // whether piece `a` ends up in the place of piece `b` after moving `k + 1` times
+            let mut powers = vec![moves.clone()];
+            while powers.len() < powers_needed {
+                let last = powers.last().unwrap();
+                let next = (0..num_pieces)
+                    .map(|a| {
+                        (0..num_pieces)
+                            .map(|b| {
+                                let vars = (0..num_pieces)
+                                    .filter_map(|c| {
+                                        Some(self.sat.and_var(&[last[a][c]?, moves[c][b]?]))
+                                    })
+                                    .collect_vec();
+                                (!vars.is_empty()).then(|| self.sat.or_var(&vars))
+                            })
+                            .collect_vec()
+                    })
+                    .collect_vec();
+                powers.push(next);
+            }
+
+            for piece in 0..num_pieces {
+                let name = self.puzzle.format_piece(PieceKey(piece));
+                for power in &powers[..self.min_cycle - 1] {
+                    if let Some(var) = power[piece][piece] {
+                        self.sat.not_clause(var);
+                    }
+                }
+                if let Some(max_cycle) = self.max_cycle {
+                    let returns_vars = powers[..max_cycle]
+                        .iter()
+                        .filter_map(|power| power[piece][piece])
+                        .collect_vec();
+                    if returns_vars.is_empty() {
+                        return Err(format!(
+                            "piece {name} can't move in a cycle of at most {max_cycle}"
+                        ));
+                    }
+                    self.sat.or_clause(&returns_vars);
+                }
+            }
+            if self.single_cycle {
+                self.add_single_cycle_rules(&moves);
+            }
+        }
+        Ok(())
+    }
+    /// the pieces that move all move in one cycle.
+    /// the first piece that moves is the root, and every other move goes to a piece with a higher rank,
+    /// so a cycle has to pass through the root to get back to where it started
+    fn add_single_cycle_rules(&mut self, moves: &[Vec<Option<Lit>>]) {
+        let num_pieces = moves.len();
+        let roots = (0..num_pieces).map(|_| self.sat.var()).collect_vec();
+        for (piece, &root) in roots.iter().enumerate() {
+            if let Some(stays) = moves[piece][piece] {
+                self.sat.nand_clause(&[root, stays]);
+            }
+            // which also rules out a second root
+            for (before, moves_before) in moves.iter().enumerate().take(piece) {
+                match moves_before[before] {
+                    Some(stays) => self.sat.implies_clause(root, stays),
+                    None => self.sat.not_clause(root),
+                }
+            }
+        }
+        // whether the rank of a piece is at least 1, 2, ...
+        let rank_at_least = (0..num_pieces)
+            .map(|_| (1..num_pieces).map(|_| self.sat.var()).collect_vec())
             .collect_vec();
-            if !matching_vars.is_empty() {
-                let matching_var = self.sat.or_var(&matching_vars);
-                self.edge_matching_vars.put(edge_a, edge_b, matching_var);
+        for ranks in &rank_at_least {
+            for (&higher, &lower) in ranks.iter().skip(1).zip(ranks) {
+                self.sat.implies_clause(higher, lower);
+            }
+        }
+        for (a, b) in (0..num_pieces).tuple_combinations() {
+            for (a, b) in [(a, b), (b, a)] {
+                let Some(var) = moves[a][b] else {
+                    continue;
+                };
+                // a piece of rank at least k moves to one of rank at least k + 1, unless that is the root
+                self.sat.or_clause(&[!var, roots[b], rank_at_least[b][0]]);
+                for k in 1..num_pieces {
+                    let mut clause = vec![!var, roots[b], !rank_at_least[a][k - 1]];
+                    clause.extend(rank_at_least[b].get(k));
+                    self.sat.or_clause(&clause);
+                }
             }
         }
     }
-    fn add_one_hot_edge_matching(&mut self) {
-        for edge_a in puzzle_edges(&self.puzzle) {
-            let matching_vars = puzzle_edges(&self.puzzle)
-                .filter_map(|edge_b| self.edge_matching_vars.get(edge_a, edge_b))
+    /// pieces only go to places of the other assemblies with their label, or without a label
+    fn add_label_rules(
+        &mut self,
+        labels: &PieceLabels,
+        target_labels: &PieceLabels,
+    ) -> Result<(), String> {
+        if let Some(&(label, needed, available)) = target_labels.missing_from(labels).first() {
+            return Err(format!(
+                "{needed} places are labeled {label} but only {available} pieces are"
+            ));
+        }
+        for assembly in 0..self.assemblies.len() {
+            for src_piece in puzzle_pieces(&self.puzzle) {
+                for dest_piece in puzzle_pieces(&self.dest) {
+                    let target = target_labels.label(dest_piece);
+                    if target.is_none() || target == labels.label(src_piece) {
+                        continue;
+                    }
+                    let vars = self.place_vars(
+                        assembly,
+                        Place::Piece(src_piece),
+                        Place::Piece(dest_piece),
+                    );
+                    self.sat.nor_clause(&vars);
+                }
+            }
+        }
+        Ok(())
+    }
+    /// moving the whole board by the symmetry before or after rearranging it gives the same result
+    fn add_symmetry(&mut self, symmetry: Symmetry) -> Result<(), String> {
+        let missing = || "the board doesn't have that symmetry".to_owned();
+        let src_map = point_symmetry(&self.puzzle, symmetry).ok_or_else(missing)?;
+        let dest_map = point_symmetry(&self.dest, symmetry).ok_or_else(missing)?;
+        for assembly in &self.assemblies {
+            for src_point in puzzle_points(&self.puzzle) {
+                for dest_point in puzzle_points(&self.dest) {
+                    let Some(var) = assembly.point_dest_vars.get(src_point, dest_point) else {
+                        continue;
+                    };
+                    // following the symmetry around, these implications chain back to `var`, so they are all equivalent
+                    match assembly
+                        .point_dest_vars
+                        .get(src_map[src_point.0], dest_map[dest_point.0])
+                    {
+                        Some(moved_var) => self.sat.implies_clause(var, moved_var),
+                        None => self.sat.not_clause(var),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// vars for a source piece or point landing on a destination piece or point
+    fn place_vars(&self, assembly: usize, src: Place, dest: Place) -> Vec<Lit> {
+        let src_point = match src {
+            Place::Piece(piece) => self.puzzle.arbitrary_point_on_piece(piece),
+            Place::Point(point) => point,
+        };
+        let dest_points = match dest {
+            Place::Piece(piece) => puzzle_points_on_piece(&self.dest, piece).collect_vec(),
+            Place::Point(point) => vec![point],
+        };
+        dest_points
+            .into_iter()
+            .filter_map(|dest_point| {
+                self.assemblies[assembly]
+                    .point_dest_vars
+                    .get(src_point, dest_point)
+            })
+            .collect()
+    }
+    /// the piece of a rule, or every piece
+    fn rule_pieces(&self, piece: Option<PieceKey>) -> Vec<PieceKey> {
+        match piece {
+            Some(piece) => vec![piece],
+            None => puzzle_pieces(&self.puzzle).collect(),
+        }
+    }
+    fn format_place(&self, puzzle: &P, place: Place) -> String {
+        match place {
+            Place::Piece(piece) => puzzle.format_piece(piece),
+            Place::Point(point) => puzzle.format_point(point),
+        }
+    }
+
+    fn add_tab_vars(&mut self) {
+        self.tab_vars = puzzle_edges(&self.puzzle).map(|_| self.sat.var()).collect();
+        // points that end adjacent must be a tab and a blank
+        for assembly in 0..self.assemblies.len() {
+            for (src_point_a, src_point_b) in puzzle_point_pairs(&self.puzzle) {
+                if self.puzzle.point_edge(src_point_a) == self.puzzle.point_edge(src_point_b) {
+                    continue;
+                }
+                let dest_adj_var = self.assemblies[assembly]
+                    .point_dest_adjacent_vars
+                    .get(src_point_a, src_point_b)
+                    .unwrap();
+                let tab_a = self.tab_var(src_point_a);
+                let tab_b = self.tab_var(src_point_b);
+                self.sat.or_clause(&[!dest_adj_var, tab_a, tab_b]);
+                self.sat.or_clause(&[!dest_adj_var, !tab_a, !tab_b]);
+            }
+        }
+    }
+    fn add_tab_rules(&mut self) {
+        for piece in puzzle_pieces(&self.puzzle) {
+            let tab_vars = puzzle_points_on_piece(&self.puzzle, piece)
+                .map(|point| self.tab_var(point))
                 .collect_vec();
-            self.sat.exact_count_clause(1, &matching_vars);
+
+            for &(points, tabs) in &self.tab_counts {
+                if points == tab_vars.len() {
+                    self.sat.exact_count_clause(tabs, &tab_vars);
+                }
+            }
+            if self.alternate_tabs && tab_vars.len().is_multiple_of(2) {
+                for (&tab_a, &tab_b) in tab_vars.iter().circular_tuple_windows() {
+                    self.sat.or_clause(&[tab_a, tab_b]);
+                    self.sat.nand_clause(&[tab_a, tab_b]);
+                }
+            }
+        }
+    }
+    /// whether the point has the tab of its edge
+    fn tab_var(&self, point: PointKey) -> Lit {
+        let edge = self.puzzle.point_edge(point);
+        let var = self.tab_vars[edge.0];
+        if point == self.puzzle.arbitrary_point_on_edge(edge) {
+            var
+        } else {
+            !var
         }
     }
 
-    fn print_point_dest(&self, solution: &SatSolution) {
+    /// suffix for printed lines, so that two assemblies print like they always have
+    fn label(&self, assembly: usize) -> String {
+        if self.assemblies.len() == 1 && self.known_assemblies == 1 {
+            String::new()
+        } else {
+            format!(" {}", assembly + self.known_assemblies + 1)
+        }
+    }
+    fn print_point_dest(&self, solution: &SatSolution, assembly: usize) {
         println!(
-            "piece dest: {}",
+            "piece dest{}: {}",
+            self.label(assembly),
             puzzle_pieces(&self.puzzle)
                 .map(|src_piece| self.puzzle.arbitrary_point_on_piece(src_piece))
-                .flat_map(|src_point| puzzle_points(&self.puzzle)
+                .flat_map(|src_point| puzzle_points(&self.dest)
                     .map(move |dest_point| (src_point, dest_point)))
                 .filter(|&(src_point, dest_point)| {
-                    self.point_dest_vars
+                    self.assemblies[assembly]
+                        .point_dest_vars
                         .get(src_point, dest_point)
                         .map(|var| solution.get(var))
                         .unwrap_or(false)
                 })
-                .map(|(edge_a, edge_b)| format!(
+                .map(|(src_point, dest_point)| format!(
                     "{}=>{}",
-                    self.puzzle.format_point(edge_a),
-                    self.puzzle.format_point(edge_b)
+                    self.puzzle.format_point(src_point),
+                    self.dest.format_point(dest_point)
                 ))
                 .format(" ")
         )
     }
-    fn print_edge_matching(&self, solution: &SatSolution) {
+    fn print_flipped_pieces(&self, solution: &SatSolution, assembly: usize) {
+        println!(
+            "flipped pieces{}: {}",
+            self.label(assembly),
+            self.assemblies[assembly]
+                .placements
+                .iter()
+                .filter(|placement| placement.flipped && solution.get(placement.var))
+                .map(|placement| self
+                    .puzzle
+                    .format_piece(self.puzzle.point_piece(placement.src_point)))
+                .format(" ")
+        );
+    }
+    fn print_edge_matching(&self, solution: &SatSolution, assembly: usize) {
         println!(
-            "edge matching: {}",
+            "edge matching{}: {}",
+            self.label(assembly),
             puzzle_edge_pairs(&self.puzzle)
                 .filter(|&(edge_a, edge_b)| {
-                    self.edge_matching_vars
+                    self.assemblies[assembly]
+                        .edge_matching_vars
                         .get(edge_a, edge_b)
                         .map(|var| solution.get(var))
                         .unwrap_or(false)
@@ -250,16 +1634,239 @@ impl<P: Puzzle> JigsawDoubler<P> {
         );
     }
 
-    fn add_prior_solution(&mut self, solution: &SatSolution) {
-        let point_dest_vars = &self.point_dest_vars;
-        let differ_vars = puzzle_pieces(&self.puzzle)
-            .map(|src_piece| self.puzzle.arbitrary_point_on_piece(src_piece))
-            .flat_map(|src_point| {
-                puzzle_points(&self.puzzle)
-                    .filter_map(move |dest_point| point_dest_vars.get(src_point, dest_point))
+    /// lengths of the cycles pieces move in, longest first
+    fn print_cycle_type(&self, solution: &SatSolution, assembly: usize) {
+        let homes = self.homes.as_ref().unwrap();
+        let assembled = self.decode(solution, assembly);
+        let moves_to = puzzle_pieces(&self.puzzle)
+            .map(|piece| {
+                let point = assembled.point_dest(self.puzzle.arbitrary_point_on_piece(piece));
+                homes[self.dest.point_piece(point).0]
             })
-            .map(|var| if solution.get(var) { !var } else { var })
             .collect_vec();
-        self.sat.or_clause(&differ_vars);
+        let mut seen = vec![false; moves_to.len()];
+        let mut lengths = vec![];
+        for start in puzzle_pieces(&self.puzzle) {
+            let mut length = 0;
+            let mut piece = start;
+            while !seen[piece.0] {
+                seen[piece.0] = true;
+                length += 1;
+                piece = moves_to[piece.0];
+            }
+            if length > 0 {
+                lengths.push(length);
+            }
+        }
+        lengths.sort_by(|a, b| b.cmp(a));
+        println!(
+            "cycle type{}: {}",
+            self.label(assembly),
+            lengths.iter().format(" ")
+        );
+    }
+
+    fn print_tabs(&self, solution: &SatSolution) {
+        println!(
+            "tabs: {}",
+            self.tab_points(solution)
+                .into_iter()
+                .map(|point| self.puzzle.format_point(point))
+                .format(" ")
+        );
+    }
+    fn tab_points(&self, solution: &SatSolution) -> Vec<PointKey> {
+        puzzle_edges(&self.puzzle)
+            .map(|edge| self.puzzle.arbitrary_point_on_edge(edge))
+            .map(|point| {
+                if solution.get(self.tab_var(point)) {
+                    point
+                } else {
+                    self.puzzle.other_point_on_edge(point)
+                }
+            })
+            .collect()
+    }
+
+    fn print_unmatched_edges(&self, solution: &SatSolution) {
+        println!(
+            "unmatched edges: {}",
+            puzzle_edges(&self.puzzle)
+                .filter(|&edge| self.may_be_unmatched(edge))
+                .filter(|&edge| solution.get(self.stays_adjacent_var(0, edge)))
+                .map(|edge| self.puzzle.format_edge(edge))
+                .format(" ")
+        );
+    }
+
+    fn decode(&self, solution: &SatSolution, assembly: usize) -> DoubledSolution {
+        let placements = puzzle_pieces(&self.puzzle)
+            .map(|src_piece| {
+                let src_point = self.puzzle.arbitrary_point_on_piece(src_piece);
+                self.assemblies[assembly]
+                    .placements
+                    .iter()
+                    .find(|placement| {
+                        placement.src_point == src_point && solution.get(placement.var)
+                    })
+                    .map(|placement| (placement.dest_point, placement.flipped))
+                    .unwrap()
+            })
+            .collect_vec();
+        let doubled = DoubledSolution::between(&self.puzzle, &self.dest, &placements);
+        if self.tabs {
+            doubled.with_tab_points(self.tab_points(solution))
+        } else {
+            doubled
+        }
+    }
+    fn write_cuts(&self, solution: &SatSolution, path: &str) -> Result<(), String> {
+        let paths = cut_paths(&self.puzzle, &self.decode(solution, 0))?;
+        for (extension, contents) in [("svg", cut_svg(&paths)), ("dxf", cut_dxf(&paths))] {
+            let path = format!("{path}.{extension}");
+            fs::write(&path, contents)
+                .map_err(|err| format!("could not write cuts {path:?}: {err}"))?;
+        }
+        println!("cuts written to {path}.svg and {path}.dxf");
+        Ok(())
+    }
+
+    /// rule out a solution, along with the same assemblies in any other order
+    /// and each of them moved by any of the symmetries
+    fn add_prior_solution(&mut self, solution: &SatSolution) {
+        // with the whole board moved, each piece goes where the symmetry moves it, turned over if mirrored
+        let identity = (puzzle_points(&self.dest).collect_vec(), false);
+        let moves = std::iter::once(&identity)
+            .chain(&self.symmetries)
+            .collect_vec();
+        let num_assemblies = self.assemblies.len();
+        let mut clauses = vec![];
+        // assemblies have their placements in the same order
+        for order in (0..num_assemblies).permutations(num_assemblies) {
+            for moved in (0..num_assemblies)
+                .map(|_| moves.iter())
+                .multi_cartesian_product()
+            {
+                let clause = self
+                    .assemblies
+                    .iter()
+                    .zip(&order)
+                    .zip(moved)
+                    .flat_map(|((assembly, &copy), (map, mirrors))| {
+                        let copies = &self.assemblies[copy].placements;
+                        assembly
+                            .placements
+                            .iter()
+                            .filter(|placement| solution.get(placement.var))
+                            .map(move |placement| {
+                                let copy = copies.iter().find(|other| {
+                                    other.src_point == placement.src_point
+                                        && other.dest_point == map[placement.dest_point.0]
+                                        && other.flipped == (placement.flipped != *mirrors)
+                                })?;
+                                Some(!copy.var)
+                            })
+                    })
+                    .collect::<Option<Vec<_>>>();
+                clauses.extend(clause);
+            }
+        }
+        for clause in clauses {
+            self.sat.or_clause(&clause);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the search main sets up for a square board and these options
+    fn try_configure(args: &[&str]) -> Result<JigsawDoubler<SquarePuzzle>, String> {
+        let options = Options::parse(args.iter().map(|&arg| arg.to_owned())).unwrap();
+        let BoardOption::Square { rows, cols } = options.board else {
+            panic!("not a square board");
+        };
+        let puzzle = square_board(rows, cols, &options);
+        JigsawDoubler::configure(puzzle.clone(), puzzle, &options, options.unmatched)
+    }
+    fn configure(args: &[&str]) -> JigsawDoubler<SquarePuzzle> {
+        try_configure(args).unwrap()
+    }
+    fn configure_board(puzzle: SquarePuzzle, options: &Options) -> JigsawDoubler<SquarePuzzle> {
+        JigsawDoubler::configure(puzzle.clone(), puzzle, options, options.unmatched).unwrap()
+    }
+    /// the objective of the best solution
+    fn best(mut s: JigsawDoubler<SquarePuzzle>, objective: Objective) -> usize {
+        let solution = s.optimize(objective).unwrap().unwrap();
+        let (_, weighted_vars) = s.objective_vars(objective);
+        weighted_vars
+            .iter()
+            .filter(|&&(_, var)| solution.get(var))
+            .map(|&(weight, _)| weight)
+            .sum()
+    }
+
+    #[test]
+    fn min_cycle_on_rectangle() {
+        let mut s = configure(&["--size", "2x4", "--min-cycle", "2"]);
+        assert!(s.sat.solve().is_some());
+    }
+
+    #[test]
+    fn optimize_rectangle_like_mask() {
+        for (name, objective) in [
+            ("fixed-points", Objective::FixedPoints),
+            ("rotations", Objective::Rotations),
+            ("displacement", Objective::Displacement),
+        ] {
+            let rectangle = configure(&["--size", "3x3", "--optimize", name]);
+            let options = Options::parse(["--optimize".to_owned(), name.to_owned()]).unwrap();
+            let mask =
+                configure_board(SquarePuzzle::parse_mask("###\n###\n###").unwrap(), &options);
+            assert_eq!(best(rectangle, objective), best(mask, objective), "{name}");
+        }
+    }
+
+    #[test]
+    fn rules_move_first_corner() {
+        // only the middle piece can't move
+        assert_eq!(
+            try_configure(&["--size", "3x3", "--constraint", "all moves"]).err(),
+            Some("piece b2 can't move".to_owned())
+        );
+        let mut s = configure(&["--size", "3x3", "--constraint", "a1 moves"]);
+        assert!(s.sat.solve().is_some());
+        let mut s = configure(&["--size", "2x4", "--constraint", "all moves"]);
+        assert!(s.sat.solve().is_some());
+    }
+
+    #[test]
+    fn symmetry_moves_first_corner() {
+        let mut s = configure(&["--size", "4x4", "--symmetry", "mirror-horizontal"]);
+        let mut count = 0;
+        while let Some(solution) = s.sat.solve() {
+            s.add_prior_solution(&solution);
+            count += 1;
+        }
+        // keeping a1 in place found only 2
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn labels_move_first_corner() {
+        let labels = std::env::temp_dir().join("jigsaw-doubler-corner-labels.txt");
+        let target_labels = std::env::temp_dir().join("jigsaw-doubler-corner-target-labels.txt");
+        fs::write(&labels, "a1=x").unwrap();
+        fs::write(&target_labels, "d4=x").unwrap();
+        let mut s = configure(&[
+            "--size",
+            "4x4",
+            "--labels",
+            labels.to_str().unwrap(),
+            "--target-labels",
+            target_labels.to_str().unwrap(),
+        ]);
+        assert!(s.sat.solve().is_some());
     }
 }
@@ -0,0 +1,602 @@
+use std::fs;
+
+use jigsaw_doubler::puzzle::Symmetry;
+use jigsaw_doubler::sat::CountEncoding;
+
+pub const USAGE: &str = "\
+usage: jigsaw-doubler [options]
+
+board:
+  --size <rows>x<cols>      search a rectangular board (default 5x5)
+  --mask <file>             search a board outlined by text art, `#` for pieces and `.` for missing cells
+  --voronoi <rows>x<cols>   search a voronoi diagram of randomly jittered points on a grid
+  --seed <seed>             random seed for generated boards (default 0)
+  --jitter <amount>         how far generated points move from the grid, up to 0.5 (default 0.4)
+  --cairo <rows>x<cols>     search a cairo pentagonal tiling of a grid of squares
+  --rhombille <rows>x<cols> search a rhombille tiling of a grid of hexagons, three rhombi each
+  --dest-size <rows>x<cols> put the pieces of a square or mask board together into a rectangle of another shape
+  --dest-mask <file>        put the pieces of a square or mask board together into another outline
+  --svg <file>              also draw the board to an svg file
+
+checking:
+  --check <file>            instead of searching, find every assembly of a cut whose shapes are given by the
+                            `edge matching:` line of a printed solution in the file, and report whether there are
+                            exactly two. `tabs:` and `piece dest:` lines are also used if present
+  --extend <file>           instead of searching, find more assemblies of a cut doubled puzzle given like for --check,
+                            other than the original and the `piece dest:` line
+
+constraints:
+  --constraint <rule>       only find solutions that follow a rule about where pieces go, may be given more than once:
+                            `b2=>d4` to put piece b2 in place of d4, `b2^=>d4>` to also fix which way it is turned,
+                            `!b2=>d4` to keep it out of d4, `b2 moves` or `all moves` to not leave pieces in place,
+                            `b2 turns`, `b2 turns 90` or `all turns` to turn pieces (clockwise, in degrees)
+  --constraints <file>      rules from a file, one per line, with `#` comments
+  --labels <file>           label pieces by the part of the picture they show, as `<piece>=<label>` (e.g. `a1=sky`)
+  --target-labels <file>    labels for the places of the other assemblies, as `<piece>=<label>`, so pieces only go
+                            to places with the same label, making a second picture. places without a label take any piece
+
+cycles:
+  --involution              pieces only swap in pairs or stay in place
+  --min-cycle <length>      pieces only move in cycles at least this long, 2 for no pieces left in place
+  --max-cycle <length>      pieces only move in cycles at most this long
+  --single-cycle            the pieces that move all move in one cycle
+
+symmetry:
+  --symmetry <symmetry>     only find rearrangements that look the same after moving the whole board by a symmetry:
+                            `rotate180`, `rotate90`, `mirror-horizontal` or `mirror-vertical`
+
+optimizing:
+  --optimize <objective>    only find solutions that are proven best for the objective:
+                            `displacement` for the most total distance pieces move,
+                            `rotations` for the most pieces turned, or `fixed-points` for the fewest pieces left in place
+
+edge matching:
+  --colors <file>           instead of a doubled jigsaw, solve an edge matching puzzle on the board, where every seam
+                            must join sides of the same colour. the file gives each point's colour as `<point>=<colour>`
+
+solving:
+  --solver-cmd <command>    solve with another SAT solver instead of the built in one, like `kissat` or `cadical -q`.
+                            the command is run on a DIMACS CNF file for every solution, and must print the model on `v` lines
+  --count-encoding <name>   how to encode how many vars are true, like exactly one place for each piece:
+                            `sequential` (default) or `totalizer` or `sorting-network` for any count,
+                            or `pairwise`, `commander`, `product` or `ladder` for at most one, with other counts sequential
+
+output:
+  --dimacs <file>           instead of solving, write the search as a DIMACS CNF file for other solvers and model counters,
+                            with comments naming the vars and a `c ind` line over where the pieces go,
+                            but not an --optimize objective
+  --opb <file>              instead of solving, write the search as an OPB file for pseudo-boolean solvers,
+                            with the --optimize objective if given
+  --interactive             instead of listing solutions, read placement rules from stdin, comma separated on each line,
+                            and show a solution that follows them or which of them conflict
+  --cut <prefix>            write laser cut paths for each solution to <prefix><n>.svg and <prefix><n>.dxf,
+                            with a different knob shape for each pair of matched edges
+
+matching:
+  --assemblies <count>      number of different ways the pieces fit together, including the original (default 2)
+  --tabs                    require each edge to have a tab on one side and a blank on the other,
+                            so matched edges must fit together tab to blank
+  --tab-count <points>:<tabs>
+                            pieces with this many neighbors have exactly this many tabs (e.g. `4:2`),
+                            may be given more than once, implies --tabs
+  --alternate-tabs          tabs and blanks alternate around pieces with an even number of neighbors,
+                            implies --tabs
+  --flip                    allow pieces to be turned over in the second assembly, for double sided puzzles
+  --unmatched <count>       number of edges that stay together in both assemblies instead of matching another edge
+                            (default 1 if the board has an odd number of edges, otherwise 0)
+  --max-unmatched <count>   allow up to this many edges to stay together, for boards without a perfect solution
+  --fewest-unmatched        find the smallest number of edges that must stay together, and all solutions with that many
+  --unmatched-edges <list>  comma separated edges allowed to be unmatched, as printed (e.g. `a1|,b2_`)
+                            (default all edges)
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardOption {
+    Square { rows: usize, cols: usize },
+    Mask(String),
+    Voronoi { rows: usize, cols: usize },
+    Cairo { rows: usize, cols: usize },
+    Rhombille { rows: usize, cols: usize },
+}
+
+/// what makes a solution better than another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// most total manhattan distance between where pieces start and end
+    Displacement,
+    /// most pieces turned or turned over
+    Rotations,
+    /// fewest pieces ending where they started
+    FixedPoints,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub board: BoardOption,
+    /// board of a different shape for the second assembly, only `Square` or `Mask`
+    pub dest: Option<BoardOption>,
+    pub seed: u64,
+    pub jitter: f64,
+    pub svg: Option<String>,
+    pub cut: Option<String>,
+    pub dimacs: Option<String>,
+    pub opb: Option<String>,
+    /// answer placement rules from stdin instead of listing solutions
+    pub interactive: bool,
+    /// external SAT solver program and arguments
+    pub solver_cmd: Option<String>,
+    pub count_encoding: CountEncoding,
+    pub check: Option<String>,
+    pub extend: Option<String>,
+    pub colors: Option<String>,
+    /// placement rules, one per line
+    pub constraints: Vec<String>,
+    /// labels of the pieces and of the places they go to, given together
+    pub labels: Option<(String, String)>,
+    pub optimize: Option<Objective>,
+    pub min_cycle: Option<usize>,
+    pub max_cycle: Option<usize>,
+    pub single_cycle: bool,
+    pub symmetry: Option<Symmetry>,
+    pub assemblies: usize,
+    pub flip: bool,
+    pub tabs: bool,
+    pub tab_counts: Vec<(usize, usize)>,
+    pub alternate_tabs: bool,
+    pub unmatched: Option<usize>,
+    pub max_unmatched: Option<usize>,
+    pub fewest_unmatched: bool,
+    pub unmatched_edges: Option<Vec<String>>,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            board: BoardOption::Square { rows: 5, cols: 5 },
+            dest: None,
+            seed: 0,
+            jitter: 0.4,
+            svg: None,
+            cut: None,
+            dimacs: None,
+            opb: None,
+            interactive: false,
+            solver_cmd: None,
+            count_encoding: CountEncoding::Sequential,
+            check: None,
+            extend: None,
+            colors: None,
+            constraints: vec![],
+            labels: None,
+            optimize: None,
+            min_cycle: None,
+            max_cycle: None,
+            single_cycle: false,
+            symmetry: None,
+            assemblies: 2,
+            flip: false,
+            tabs: false,
+            tab_counts: vec![],
+            alternate_tabs: false,
+            unmatched: None,
+            max_unmatched: None,
+            fewest_unmatched: false,
+            unmatched_edges: None,
+        }
+    }
+}
+impl Options {
+    /// parse command line arguments, not including the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let (mut labels, mut target_labels) = (None, None);
+        let mut involution = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "--size" => {
+                    let (rows, cols) = parse_square_size(&value()?)?;
+                    options.board = BoardOption::Square { rows, cols };
+                }
+                "--mask" => {
+                    let path = value()?;
+                    let art = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read mask {path:?}: {err}"))?;
+                    options.board = BoardOption::Mask(art);
+                }
+                "--dest-size" => {
+                    let (rows, cols) = parse_square_size(&value()?)?;
+                    options.dest = Some(BoardOption::Square { rows, cols });
+                }
+                "--dest-mask" => {
+                    let path = value()?;
+                    let art = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read mask {path:?}: {err}"))?;
+                    options.dest = Some(BoardOption::Mask(art));
+                }
+                "--voronoi" => {
+                    let (rows, cols) = parse_size(&value()?)?;
+                    options.board = BoardOption::Voronoi { rows, cols };
+                }
+                "--cairo" => {
+                    let (rows, cols) = parse_size(&value()?)?;
+                    options.board = BoardOption::Cairo { rows, cols };
+                }
+                "--rhombille" => {
+                    let (rows, cols) = parse_size(&value()?)?;
+                    options.board = BoardOption::Rhombille { rows, cols };
+                }
+                "--svg" => options.svg = Some(value()?),
+                "--cut" => options.cut = Some(value()?),
+                "--dimacs" => options.dimacs = Some(value()?),
+                "--opb" => options.opb = Some(value()?),
+                "--interactive" => options.interactive = true,
+                "--solver-cmd" => options.solver_cmd = Some(value()?),
+                "--count-encoding" => {
+                    options.count_encoding = match value()?.as_str() {
+                        "sequential" => CountEncoding::Sequential,
+                        "pairwise" => CountEncoding::Pairwise,
+                        "commander" => CountEncoding::Commander,
+                        "product" => CountEncoding::Product,
+                        "ladder" => CountEncoding::Ladder,
+                        "totalizer" => CountEncoding::Totalizer,
+                        "sorting-network" => CountEncoding::SortingNetwork,
+                        encoding => return Err(format!("unknown count encoding {encoding:?}")),
+                    }
+                }
+                "--check" => {
+                    let path = value()?;
+                    let text = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read solution {path:?}: {err}"))?;
+                    options.check = Some(text);
+                }
+                "--constraint" => options.constraints.push(value()?),
+                "--constraints" => {
+                    let path = value()?;
+                    let text = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read constraints {path:?}: {err}"))?;
+                    options.constraints.push(text);
+                }
+                "--labels" | "--target-labels" => {
+                    let path = value()?;
+                    let text = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read labels {path:?}: {err}"))?;
+                    match arg.as_str() {
+                        "--labels" => labels = Some(text),
+                        _ => target_labels = Some(text),
+                    }
+                }
+                "--optimize" => {
+                    options.optimize = Some(match value()?.as_str() {
+                        "displacement" => Objective::Displacement,
+                        "rotations" => Objective::Rotations,
+                        "fixed-points" => Objective::FixedPoints,
+                        objective => return Err(format!("unknown objective {objective:?}")),
+                    })
+                }
+                "--involution" => involution = true,
+                "--min-cycle" => options.min_cycle = Some(parse_number(&value()?)?),
+                "--max-cycle" => {
+                    options.max_cycle = Some(parse_number(&value()?)?);
+                    if options.max_cycle == Some(0) {
+                        return Err("cycles are at least 1 long".to_owned());
+                    }
+                }
+                "--single-cycle" => options.single_cycle = true,
+                "--symmetry" => {
+                    options.symmetry = Some(match value()?.as_str() {
+                        "rotate180" => Symmetry::HalfTurn,
+                        "rotate90" => Symmetry::QuarterTurn,
+                        "mirror-horizontal" => Symmetry::MirrorHorizontal,
+                        "mirror-vertical" => Symmetry::MirrorVertical,
+                        symmetry => return Err(format!("unknown symmetry {symmetry:?}")),
+                    })
+                }
+                "--extend" => {
+                    let path = value()?;
+                    let text = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read solution {path:?}: {err}"))?;
+                    options.extend = Some(text);
+                }
+                "--colors" => {
+                    let path = value()?;
+                    let text = fs::read_to_string(&path)
+                        .map_err(|err| format!("could not read colours {path:?}: {err}"))?;
+                    options.colors = Some(text);
+                }
+                "--seed" => options.seed = parse_number(&value()?)?,
+                "--jitter" => {
+                    options.jitter = parse_number(&value()?)?;
+                    if !(0.0..=0.5).contains(&options.jitter) {
+                        return Err(format!(
+                            "jitter {} is not between 0 and 0.5",
+                            options.jitter
+                        ));
+                    }
+                }
+                "--assemblies" => {
+                    options.assemblies = parse_number(&value()?)?;
+                    if options.assemblies < 2 {
+                        return Err("there must be at least 2 assemblies".to_owned());
+                    }
+                }
+                "--flip" => options.flip = true,
+                "--tabs" => options.tabs = true,
+                "--tab-count" => {
+                    let rule = value()?;
+                    let (points, tabs) = rule.split_once(':').ok_or_else(|| {
+                        format!("invalid tab count {rule:?}, expected <points>:<tabs>")
+                    })?;
+                    let (points, tabs) = (parse_number(points)?, parse_number(tabs)?);
+                    if tabs > points {
+                        return Err(format!("tab count {rule:?} has more tabs than points"));
+                    }
+                    options.tab_counts.push((points, tabs));
+                }
+                "--alternate-tabs" => options.alternate_tabs = true,
+                "--unmatched" => options.unmatched = Some(parse_number(&value()?)?),
+                "--max-unmatched" => options.max_unmatched = Some(parse_number(&value()?)?),
+                "--fewest-unmatched" => options.fewest_unmatched = true,
+                "--unmatched-edges" => {
+                    let edges = value()?;
+                    options.unmatched_edges = Some(edges.split(',').map(str::to_owned).collect());
+                }
+                _ => return Err(format!("unknown option {arg:?}")),
+            }
+        }
+        options.labels = match (labels, target_labels) {
+            (Some(labels), Some(target_labels)) => Some((labels, target_labels)),
+            (None, None) => None,
+            _ => return Err("--labels and --target-labels must be given together".to_owned()),
+        };
+        if involution {
+            if options.max_cycle.is_some() {
+                return Err("--involution can't be combined with --max-cycle".to_owned());
+            }
+            options.max_cycle = Some(2);
+        }
+        let unmatched_modes = [
+            options.unmatched.is_some(),
+            options.max_unmatched.is_some(),
+            options.fewest_unmatched,
+        ];
+        if unmatched_modes.iter().filter(|&&mode| mode).count() > 1 {
+            return Err(
+                "--unmatched, --max-unmatched and --fewest-unmatched can't be combined".to_owned(),
+            );
+        }
+        let modes = [
+            options.check.is_some(),
+            options.extend.is_some(),
+            options.colors.is_some(),
+        ];
+        if modes.iter().filter(|&&mode| mode).count() > 1 {
+            return Err("--check, --extend and --colors can't be combined".to_owned());
+        }
+        if options.dimacs.is_some() && modes.contains(&true) {
+            return Err(
+                "--dimacs only writes the search, not --check, --extend or --colors".to_owned(),
+            );
+        }
+        if options.dimacs.is_some() && options.optimize.is_some() {
+            return Err(
+                "--dimacs can't write the --optimize objective, use --opb instead".to_owned(),
+            );
+        }
+        if options.interactive && (options.dimacs.is_some() || options.opb.is_some()) {
+            return Err("--interactive can't be combined with --dimacs or --opb".to_owned());
+        }
+        if options.dest.is_some()
+            && !matches!(
+                options.board,
+                BoardOption::Square { .. } | BoardOption::Mask(_)
+            )
+        {
+            return Err("a destination board needs a square or mask board".to_owned());
+        }
+        Ok(options)
+    }
+
+    /// whether some option only holds for some turned copies of a solution,
+    /// so keeping the first corner of a rectangle in place would lose solutions
+    pub fn turns_matter(&self) -> bool {
+        self.min_cycle.is_some()
+            || self.max_cycle.is_some()
+            || self.single_cycle
+            || self.labels.is_some()
+            || self.symmetry.is_some()
+            || self.optimize.is_some()
+            || !self.constraints.is_empty()
+            || self.interactive
+    }
+}
+
+fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    let (rows, cols) = size
+        .split_once('x')
+        .and_then(|(rows, cols)| Some((rows.parse().ok()?, cols.parse().ok()?)))
+        .ok_or_else(|| format!("invalid size {size:?}, expected <rows>x<cols>"))?;
+    if rows == 0 || cols == 0 {
+        return Err(format!(
+            "invalid size {size:?}, rows and columns must be at least 1"
+        ));
+    }
+    Ok((rows, cols))
+}
+
+/// a square board also needs two pieces, since a single piece has no edges
+fn parse_square_size(size: &str) -> Result<(usize, usize), String> {
+    let (rows, cols) = parse_size(size)?;
+    if rows * cols < 2 {
+        return Err(format!(
+            "invalid size {size:?}, a square board needs at least two pieces"
+        ));
+    }
+    Ok((rows, cols))
+}
+fn parse_number<T: std::str::FromStr>(number: &str) -> Result<T, String> {
+    number
+        .parse()
+        .map_err(|_| format!("invalid number {number:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn size() {
+        let options = parse(&["--size", "3x4"]).unwrap();
+        assert_eq!(options.board, BoardOption::Square { rows: 3, cols: 4 });
+        assert!(parse(&["--size", "3"]).is_err());
+        assert!(parse(&["--size"]).is_err());
+        assert!(parse(&["--size", "1x1"]).is_err());
+        assert!(parse(&["--size", "0x5"]).is_err());
+        assert!(parse(&["--dest-size", "1x1"]).is_err());
+        assert_eq!(
+            parse(&["--size", "1x2"]).unwrap().board,
+            BoardOption::Square { rows: 1, cols: 2 }
+        );
+    }
+
+    #[test]
+    fn dest() {
+        let options = parse(&["--size", "2x6", "--dest-size", "3x4"]).unwrap();
+        assert_eq!(options.board, BoardOption::Square { rows: 2, cols: 6 });
+        assert_eq!(options.dest, Some(BoardOption::Square { rows: 3, cols: 4 }));
+        assert!(parse(&["--cairo", "2x3", "--dest-size", "3x4"]).is_err());
+    }
+
+    #[test]
+    fn voronoi() {
+        let options = parse(&["--voronoi", "3x4", "--seed", "12", "--jitter", "0.25"]).unwrap();
+        assert_eq!(options.board, BoardOption::Voronoi { rows: 3, cols: 4 });
+        assert_eq!(options.seed, 12);
+        assert_eq!(options.jitter, 0.25);
+        assert!(parse(&["--jitter", "0.6"]).is_err());
+    }
+
+    #[test]
+    fn tilings() {
+        let options = parse(&["--cairo", "2x3", "--svg", "board.svg"]).unwrap();
+        assert_eq!(options.board, BoardOption::Cairo { rows: 2, cols: 3 });
+        assert_eq!(options.svg.as_deref(), Some("board.svg"));
+        assert_eq!(options.cut, None);
+        let options = parse(&["--rhombille", "1x2"]).unwrap();
+        assert_eq!(options.board, BoardOption::Rhombille { rows: 1, cols: 2 });
+    }
+
+    #[test]
+    fn flags() {
+        assert!(parse(&["--flip"]).unwrap().flip);
+        assert!(parse(&["--tabs"]).unwrap().tabs);
+        assert!(parse(&["--alternate-tabs"]).unwrap().alternate_tabs);
+    }
+
+    #[test]
+    fn tab_counts() {
+        let options = parse(&["--tab-count", "4:2", "--tab-count", "3:1"]).unwrap();
+        assert_eq!(options.tab_counts, vec![(4, 2), (3, 1)]);
+        assert!(parse(&["--tab-count", "4"]).is_err());
+        assert!(parse(&["--tab-count", "2:3"]).is_err());
+    }
+
+    #[test]
+    fn constraints() {
+        let options = parse(&["--constraint", "all moves", "--constraint", "b2=>c3"]).unwrap();
+        assert_eq!(options.constraints, vec!["all moves", "b2=>c3"]);
+        assert!(parse(&["--constraints", "/nonexistent/rules.txt"]).is_err());
+    }
+
+    #[test]
+    fn labels() {
+        assert!(parse(&["--labels", "/nonexistent/labels.txt"]).is_err());
+        let path = std::env::temp_dir().join("jigsaw-doubler-labels-test.txt");
+        fs::write(&path, "a1=sky").unwrap();
+        let path = path.to_str().unwrap();
+        assert!(parse(&["--labels", path]).is_err());
+        let options = parse(&["--labels", path, "--target-labels", path]).unwrap();
+        assert_eq!(
+            options.labels,
+            Some(("a1=sky".to_owned(), "a1=sky".to_owned()))
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let options = parse(&["--involution", "--min-cycle", "2"]).unwrap();
+        assert_eq!(options.max_cycle, Some(2));
+        assert_eq!(options.min_cycle, Some(2));
+        assert!(!options.single_cycle);
+        assert!(parse(&["--single-cycle"]).unwrap().single_cycle);
+        assert!(parse(&["--max-cycle", "0"]).is_err());
+        assert!(parse(&["--involution", "--max-cycle", "3"]).is_err());
+        assert!(parse(&["--max-cycle", "3", "--involution"]).is_err());
+    }
+
+    #[test]
+    fn symmetry() {
+        let options = parse(&["--symmetry", "rotate180"]).unwrap();
+        assert_eq!(options.symmetry, Some(Symmetry::HalfTurn));
+        assert!(parse(&["--symmetry", "rotate45"]).is_err());
+    }
+
+    #[test]
+    fn optimize() {
+        let options = parse(&["--optimize", "fixed-points"]).unwrap();
+        assert_eq!(options.optimize, Some(Objective::FixedPoints));
+        assert!(parse(&["--optimize", "speed"]).is_err());
+    }
+
+    #[test]
+    fn assemblies() {
+        assert_eq!(parse(&["--assemblies", "3"]).unwrap().assemblies, 3);
+        assert!(parse(&["--assemblies", "1"]).is_err());
+    }
+
+    #[test]
+    fn dimacs() {
+        let options = parse(&["--dimacs", "out.cnf"]).unwrap();
+        assert_eq!(options.dimacs.as_deref(), Some("out.cnf"));
+        assert!(parse(&["--dimacs"]).is_err());
+        assert!(parse(&["--dimacs", "out.cnf", "--optimize", "rotations"]).is_err());
+        let options = parse(&["--opb", "out.opb", "--optimize", "rotations"]).unwrap();
+        assert_eq!(options.opb.as_deref(), Some("out.opb"));
+        let options = parse(&["--solver-cmd", "cadical -q"]).unwrap();
+        assert_eq!(options.solver_cmd.as_deref(), Some("cadical -q"));
+        let options = parse(&["--count-encoding", "sorting-network"]).unwrap();
+        assert_eq!(options.count_encoding, CountEncoding::SortingNetwork);
+        assert!(parse(&["--count-encoding", "binary"]).is_err());
+        assert!(parse(&["--interactive"]).unwrap().interactive);
+        assert!(parse(&["--interactive", "--dimacs", "out.cnf"]).is_err());
+    }
+
+    #[test]
+    fn unmatched() {
+        let options = parse(&["--unmatched", "3", "--unmatched-edges", "a1|,b2_"]).unwrap();
+        assert_eq!(options.unmatched, Some(3));
+        assert!(!options.flip);
+        assert_eq!(
+            options.unmatched_edges,
+            Some(vec!["a1|".to_owned(), "b2_".to_owned()])
+        );
+        assert_eq!(
+            parse(&["--max-unmatched", "2"]).unwrap().max_unmatched,
+            Some(2)
+        );
+        assert!(parse(&["--fewest-unmatched"]).unwrap().fewest_unmatched);
+        assert!(parse(&["--unmatched", "1", "--fewest-unmatched"]).is_err());
+    }
+}
@@ -1,9 +1,16 @@
 use std::fmt::Debug;
 use std::hash::Hash;
 
+pub use geometry::*;
 pub use square::*;
+pub use tiling::*;
 
+mod cairo;
+mod geometry;
+mod rhombille;
 mod square;
+mod tiling;
+mod voronoi;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PieceKey(pub usize);
@@ -29,6 +36,10 @@ pub struct PointKey(pub usize);
 /// - `can_exchange(x,y) && can_exchange(y,z)` implies `can_exchange(x,z)` for all `x`, `y`, `z`
 /// - `can_exchange(x,y)` implies `can_exchange(next_point_on_piece(x),next_point_on_piece(y))` for all `x`, `y`
 /// - if `x` and `y` are exchanged then `next_point_on_piece(x)` and `next_point_on_piece(y)` are exchanged (slightly stronger than above but not automatically verifiable)
+/// - `can_exchange_flipped(x,y)` implies `can_exchange_flipped(y,x)` for all `x`, `y`
+/// - `can_exchange_flipped(x,y) && can_exchange(y,z)` implies `can_exchange_flipped(x,z)` for all `x`, `y`, `z`
+/// - `can_exchange_flipped(x,y)` implies `can_exchange_flipped(next_point_on_piece(x),previous_point_on_piece(y))` for all `x`, `y`
+/// - `can_exchange_into(x,self,y,false) == can_exchange(x,y)` and `can_exchange_into(x,self,y,true) == can_exchange_flipped(x,y)` for all `x`, `y`
 ///
 /// in theory this could be reduced to `num_points`, `next_point_on_piece`, `other_point_on_edge`, and `can_exchange`.
 /// but puzzle implementations can more efficiently deal with the `Piece` and `Edge` equivalence classes since they are most likely used internally anyway
@@ -42,13 +53,38 @@ pub trait Puzzle {
     fn arbitrary_point_on_piece(&self, piece: PieceKey) -> PointKey;
     fn next_point_on_piece(&self, point: PointKey) -> PointKey;
     fn point_piece(&self, point: PointKey) -> PieceKey;
+    fn previous_point_on_piece(&self, point: PointKey) -> PointKey {
+        let mut previous = point;
+        loop {
+            let next = self.next_point_on_piece(previous);
+            if next == point {
+                return previous;
+            }
+            previous = next;
+        }
+    }
 
     fn arbitrary_point_on_edge(&self, edge: EdgeKey) -> PointKey;
     fn other_point_on_edge(&self, point: PointKey) -> PointKey;
     fn point_edge(&self, point: PointKey) -> EdgeKey;
 
     fn can_exchange(&self, point_a: PointKey, point_b: PointKey) -> bool;
+    /// whether the piece of `point_a` can be turned over and put in place of the piece of `point_b`,
+    /// with `point_a` moving to `point_b`. points then go around the piece in the opposite direction
+    fn can_exchange_flipped(&self, point_a: PointKey, point_b: PointKey) -> bool;
+    /// whether the piece of `point_a` can be put in place of the piece of `point_b` on `other`,
+    /// a board of the same kind with a possibly different outline, with `point_a` moving to `point_b`
+    fn can_exchange_into(
+        &self,
+        point_a: PointKey,
+        other: &Self,
+        point_b: PointKey,
+        flipped: bool,
+    ) -> bool
+    where
+        Self: Sized;
 
+    fn format_piece(&self, piece: PieceKey) -> String;
     fn format_point(&self, point: PointKey) -> String;
     fn format_edge(&self, edge: EdgeKey) -> String;
 }
@@ -57,9 +93,16 @@ pub fn puzzle_pieces(puzzle: &impl Puzzle) -> impl Iterator<Item = PieceKey> {
     (0..puzzle.num_pieces()).map(PieceKey)
 }
 
+pub fn puzzle_find_piece(puzzle: &impl Puzzle, name: &str) -> Option<PieceKey> {
+    puzzle_pieces(puzzle).find(|&piece| puzzle.format_piece(piece) == name)
+}
+
 pub fn puzzle_edges(puzzle: &impl Puzzle) -> impl Iterator<Item = EdgeKey> {
     (0..puzzle.num_edges()).map(EdgeKey)
 }
+pub fn puzzle_find_edge(puzzle: &impl Puzzle, name: &str) -> Option<EdgeKey> {
+    puzzle_edges(puzzle).find(|&edge| puzzle.format_edge(edge) == name)
+}
 pub fn puzzle_edge_pairs(puzzle: &impl Puzzle) -> impl Iterator<Item = (EdgeKey, EdgeKey)> + '_ {
     (0..puzzle.num_edges()).flat_map(move |a| (0..a).map(move |b| (EdgeKey(a), EdgeKey(b))))
 }
@@ -70,6 +113,9 @@ pub fn puzzle_points(puzzle: &impl Puzzle) -> impl Iterator<Item = PointKey> {
 pub fn puzzle_point_pairs(puzzle: &impl Puzzle) -> impl Iterator<Item = (PointKey, PointKey)> + '_ {
     (0..puzzle.num_points()).flat_map(move |a| (0..a).map(move |b| (PointKey(a), PointKey(b))))
 }
+pub fn puzzle_find_point(puzzle: &impl Puzzle, name: &str) -> Option<PointKey> {
+    puzzle_points(puzzle).find(|&point| puzzle.format_point(point) == name)
+}
 pub fn puzzle_exchange_points(
     puzzle: &impl Puzzle,
     point: PointKey,
@@ -79,16 +125,121 @@ pub fn puzzle_exchange_points(
         .filter(move |&other| puzzle.can_exchange(point, other))
 }
 
-// pub fn puzzle_points_on_piece(
-//     puzzle: &impl Puzzle,
-//     piece: PieceKey,
-// ) -> impl Iterator<Item = PointKey> + '_ {
-//     let start_point = puzzle.arbitrary_point_on_piece(piece);
-//     std::iter::successors(Some(start_point), move |&point| {
-//         if point == start_point {
-//             None
-//         } else {
-//             Some(puzzle.next_point_on_piece(point))
-//         }
-//     })
-// }
+/// identifies a piece's shape up to rotation, and a point's position within that shape.
+///
+/// `sides` lists every side of the piece in `next_point_on_piece` order, including flat sides that have no point.
+/// returns the lexicographically smallest rotation of `sides`
+/// and the offset of `side` within it, modulo the rotational period of the pattern.
+/// so two points can be exchanged if they have equal results.
+pub fn cyclic_orbit<T: Ord + Clone>(sides: &[T], side: usize) -> (Vec<T>, usize) {
+    let n = sides.len();
+    assert!(side < n);
+    let rotated = |r: usize| {
+        sides[r..]
+            .iter()
+            .chain(&sides[..r])
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let (start, canonical) = (0..n)
+        .map(|r| (r, rotated(r)))
+        .min_by(|(_, a), (_, b)| a.cmp(b))
+        .unwrap();
+    let period = (1..n).find(|&r| rotated(r) == sides).unwrap_or(n);
+    let offset = (side + n - start) % n % period;
+    (canonical, offset)
+}
+
+/// points of the piece in `next_point_on_piece` order, starting at `arbitrary_point_on_piece`
+pub fn puzzle_points_on_piece(
+    puzzle: &impl Puzzle,
+    piece: PieceKey,
+) -> impl Iterator<Item = PointKey> + '_ {
+    let start_point = puzzle.arbitrary_point_on_piece(piece);
+    std::iter::successors(Some(start_point), move |&point| {
+        Some(puzzle.next_point_on_piece(point)).filter(|&next| next != start_point)
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    /// check the constraints documented on `Puzzle`
+    pub fn assert_well_defined(puzzle: &impl Puzzle) {
+        let mut seen = vec![false; puzzle.num_points()];
+        for piece in puzzle_pieces(puzzle) {
+            for point in puzzle_points_on_piece(puzzle, piece) {
+                assert_eq!(puzzle.point_piece(point), piece);
+                assert!(!seen[point.0], "point {point:?} is on multiple pieces");
+                seen[point.0] = true;
+            }
+        }
+        assert!(seen.iter().all(|&s| s), "some points are not on a piece");
+
+        for edge in puzzle_edges(puzzle) {
+            let point = puzzle.arbitrary_point_on_edge(edge);
+            let other = puzzle.other_point_on_edge(point);
+            assert_ne!(point, other);
+            assert_eq!(puzzle.point_edge(point), edge);
+            assert_eq!(puzzle.point_edge(other), edge);
+            assert_eq!(puzzle.other_point_on_edge(other), point);
+        }
+
+        let points = puzzle_points(puzzle).collect_vec();
+        for &a in &points {
+            assert!(puzzle.can_exchange(a, a));
+            for &b in &points {
+                if !puzzle.can_exchange(a, b) {
+                    continue;
+                }
+                assert!(puzzle
+                    .can_exchange(puzzle.next_point_on_piece(a), puzzle.next_point_on_piece(b)));
+                for &c in &points {
+                    if puzzle.can_exchange(b, c) {
+                        assert!(puzzle.can_exchange(a, c));
+                    }
+                }
+            }
+            assert_eq!(
+                puzzle.next_point_on_piece(puzzle.previous_point_on_piece(a)),
+                a
+            );
+            for &b in &points {
+                assert_eq!(
+                    puzzle.can_exchange_into(a, puzzle, b, false),
+                    puzzle.can_exchange(a, b)
+                );
+                assert_eq!(
+                    puzzle.can_exchange_into(a, puzzle, b, true),
+                    puzzle.can_exchange_flipped(a, b)
+                );
+            }
+            for &b in &points {
+                if !puzzle.can_exchange_flipped(a, b) {
+                    continue;
+                }
+                assert!(puzzle.can_exchange_flipped(b, a));
+                assert!(puzzle.can_exchange_flipped(
+                    puzzle.next_point_on_piece(a),
+                    puzzle.previous_point_on_piece(b)
+                ));
+                for &c in &points {
+                    if puzzle.can_exchange(b, c) {
+                        assert!(puzzle.can_exchange_flipped(a, c));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cyclic_orbit_rotations() {
+        assert_eq!(cyclic_orbit(&[1, 0, 0, 1], 0), (vec![0, 0, 1, 1], 3));
+        assert_eq!(cyclic_orbit(&[0, 1, 1, 0], 2), (vec![0, 0, 1, 1], 3));
+        assert_eq!(cyclic_orbit(&[1, 0, 1, 0], 0), (vec![0, 1, 0, 1], 1));
+        assert_eq!(cyclic_orbit(&[1, 0, 1, 0], 2), (vec![0, 1, 0, 1], 1));
+        assert_eq!(cyclic_orbit(&[1, 1, 1, 1], 3), (vec![1, 1, 1, 1], 0));
+    }
+}
@@ -0,0 +1,155 @@
+use itertools::Itertools;
+
+use super::{TilingPuzzle, Vec2};
+
+impl TilingPuzzle {
+    /// cairo pentagonal tiling over a `rows` x `cols` grid of unit squares.
+    ///
+    /// each square is split in four by a short segment through its center,
+    /// horizontal or vertical in a checkerboard pattern, joined to the square's corners.
+    /// every pentagon is the union of the two parts on either side of an interior grid line,
+    /// so the board's outline follows the parts left over along the outside of the grid
+    pub fn cairo(rows: usize, cols: usize) -> Result<Self, String> {
+        // half the length of the segment, chosen so that all sides of the pentagons are equal
+        let a = (7f64.sqrt() - 1.0) / 6.0;
+        // ends of the segment through the center of a square
+        let segment = |row: usize, col: usize| {
+            let center = Vec2::new(col as f64 + 0.5, row as f64 + 0.5);
+            let offset = if (row + col).is_multiple_of(2) {
+                Vec2::new(a, 0.0)
+            } else {
+                Vec2::new(0.0, a)
+            };
+            [center - offset, center + offset]
+        };
+        // corners of the part of a square next to the grid line from `start` to `end`, ordered from `start`
+        let part = |row: usize, col: usize, start: Vec2, end: Vec2| {
+            let mut ends = segment(row, col).to_vec();
+            let along = end - start;
+            let parallel = (ends[1] - ends[0]).cross(along).abs() < 1e-9;
+            if !parallel {
+                // triangle, only the nearer end of the segment
+                let mid = (start + end) * 0.5;
+                ends.sort_by(|p, q| (*p - mid).length().total_cmp(&(*q - mid).length()));
+                ends.truncate(1);
+            }
+            ends.sort_by(|p, q| (*p - start).length().total_cmp(&(*q - start).length()));
+            ends
+        };
+
+        let mut polygons = vec![];
+        // horizontal grid lines between rows
+        for row in 1..rows {
+            for col in 0..cols {
+                let start = Vec2::new(col as f64, row as f64);
+                let end = Vec2::new(col as f64 + 1.0, row as f64);
+                let mut corners = vec![start];
+                corners.extend(part(row - 1, col, start, end));
+                corners.push(end);
+                corners.extend(part(row, col, end, start));
+                polygons.push(corners);
+            }
+        }
+        // vertical grid lines between columns
+        for row in 0..rows {
+            for col in 1..cols {
+                let start = Vec2::new(col as f64, row as f64);
+                let end = Vec2::new(col as f64, row as f64 + 1.0);
+                let mut corners = vec![start];
+                corners.extend(part(row, col, start, end));
+                corners.push(end);
+                corners.extend(part(row, col - 1, end, start));
+                polygons.push(corners);
+            }
+        }
+        // narrow grids leave pentagons that touch no others
+        let key = |p: Vec2| ((p.x * 1e6).round() as i64, (p.y * 1e6).round() as i64);
+        let side_keys = |corners: &[Vec2]| {
+            corners
+                .iter()
+                .circular_tuple_windows()
+                .map(|(&p, &q)| [key(p), key(q)].into_iter().sorted().collect_vec())
+                .collect_vec()
+        };
+        let side_counts = polygons
+            .iter()
+            .flat_map(|corners| side_keys(corners))
+            .counts();
+        let isolated = polygons
+            .iter()
+            .any(|corners| side_keys(corners).iter().all(|side| side_counts[side] == 1));
+        if polygons.len() < 2 || isolated {
+            return Err(format!(
+                "a {rows}x{cols} cairo tiling has pieces with no neighbors, try a bigger grid"
+            ));
+        }
+        Ok(Self::from_polygons(polygons))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::puzzle::tests::assert_well_defined;
+    use crate::puzzle::*;
+
+    #[test]
+    fn well_defined() {
+        let puzzle = TilingPuzzle::cairo(3, 4).unwrap();
+        assert_well_defined(&puzzle);
+        // one pentagon per interior grid line
+        assert_eq!(puzzle.num_pieces(), 2 * 4 + 3 * 3);
+        for tile in puzzle.tiles() {
+            assert_eq!(tile.corners.len(), 5);
+        }
+    }
+
+    #[test]
+    fn equilateral() {
+        let puzzle = TilingPuzzle::cairo(2, 2).unwrap();
+        for piece in puzzle_pieces(&puzzle) {
+            let corners = puzzle.piece_corners(piece);
+            for i in 0..corners.len() {
+                let side = corners[(i + 1) % corners.len()] - corners[i];
+                assert!((side.length() - (corners[1] - corners[0]).length()).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn interior_pentagons_exchange() {
+        let puzzle = TilingPuzzle::cairo(4, 4).unwrap();
+        assert_well_defined(&puzzle);
+        // pentagons with no border sides are all rotations of each other
+        let interior = puzzle_pieces(&puzzle)
+            .filter(|&piece| {
+                puzzle.tiles()[piece.0]
+                    .neighbors
+                    .iter()
+                    .all(Option::is_some)
+            })
+            .collect::<Vec<_>>();
+        assert!(interior.len() >= 4);
+        let point = puzzle.arbitrary_point_on_piece(interior[0]);
+        for &piece in &interior {
+            let start = puzzle.arbitrary_point_on_piece(piece);
+            let mut other = start;
+            let mut count = 0;
+            loop {
+                count += puzzle.can_exchange(point, other) as usize;
+                other = puzzle.next_point_on_piece(other);
+                if other == start {
+                    break;
+                }
+            }
+            assert_eq!(count, 1);
+        }
+    }
+
+    #[test]
+    fn too_narrow() {
+        for (rows, cols) in [(1, 1), (1, 2), (2, 1), (3, 1), (1, 4)] {
+            assert!(TilingPuzzle::cairo(rows, cols).is_err(), "{rows}x{cols}");
+        }
+        assert!(TilingPuzzle::cairo(1, 3).is_ok());
+    }
+}
@@ -0,0 +1,208 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use super::{puzzle_pieces, puzzle_points, PieceKey, PointKey, Puzzle};
+
+/// a puzzle laid out in the plane, so it can be drawn
+pub trait PuzzleGeometry: Puzzle {
+    /// corners of the piece in clockwise order (with y pointing down),
+    /// including corners between flat sides
+    fn piece_corners(&self, piece: PieceKey) -> Vec<Vec2>;
+    /// start and end of the side of its piece that the point is on, in clockwise order
+    fn point_side(&self, point: PointKey) -> (Vec2, Vec2);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+impl Vec2 {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+    pub fn dot(self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+    pub fn cross(self, other: Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+    /// rotated a quarter turn clockwise (with y pointing down)
+    pub fn perp(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+}
+impl Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+impl Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+impl Neg for Vec2 {
+    type Output = Vec2;
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+impl Mul<f64> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, scale: f64) -> Vec2 {
+        Vec2::new(self.x * scale, self.y * scale)
+    }
+}
+
+/// signed area, positive for clockwise corners with y pointing down
+pub fn area(corners: &[Vec2]) -> f64 {
+    let n = corners.len();
+    (0..n)
+        .map(|i| corners[i].cross(corners[(i + 1) % n]))
+        .sum::<f64>()
+        / 2.0
+}
+
+pub fn centroid(corners: &[Vec2]) -> Vec2 {
+    let n = corners.len();
+    let (sum, weight) = (0..n)
+        .map(|i| {
+            let (a, b) = (corners[i], corners[(i + 1) % n]);
+            ((a + b) * a.cross(b), a.cross(b))
+        })
+        .fold((Vec2::default(), 0.0), |(sum, weight), (p, w)| {
+            (sum + p, weight + w)
+        });
+    sum * (1.0 / (3.0 * weight))
+}
+
+/// a way the whole board can be moved onto itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// turned 180 degrees around its center
+    HalfTurn,
+    /// turned 90 degrees clockwise around its center
+    QuarterTurn,
+    /// mirrored left to right
+    MirrorHorizontal,
+    /// mirrored top to bottom
+    MirrorVertical,
+}
+impl Symmetry {
+    fn apply(self, p: Vec2, center: Vec2) -> Vec2 {
+        let d = p - center;
+        let moved = match self {
+            Symmetry::HalfTurn => -d,
+            Symmetry::QuarterTurn => d.perp(),
+            Symmetry::MirrorHorizontal => Vec2::new(-d.x, d.y),
+            Symmetry::MirrorVertical => Vec2::new(d.x, -d.y),
+        };
+        center + moved
+    }
+    fn reflects(self) -> bool {
+        matches!(self, Symmetry::MirrorHorizontal | Symmetry::MirrorVertical)
+    }
+}
+
+/// where the symmetry moves each point, `None` if the board doesn't have the symmetry.
+/// mirrored pieces go around in the opposite direction, so their sides are matched in reverse
+pub fn point_symmetry(puzzle: &impl PuzzleGeometry, symmetry: Symmetry) -> Option<Vec<PointKey>> {
+    let centers = puzzle_pieces(puzzle)
+        .map(|piece| centroid(&puzzle.piece_corners(piece)))
+        .collect::<Vec<_>>();
+    let center =
+        centers.iter().fold(Vec2::default(), |sum, &c| sum + c) * (1.0 / centers.len() as f64);
+    let close = |a: Vec2, b: Vec2| (a - b).length() < 1e-6;
+    puzzle_points(puzzle)
+        .map(|point| {
+            let (start, end) = puzzle.point_side(point);
+            let (mut start, mut end) = (symmetry.apply(start, center), symmetry.apply(end, center));
+            if symmetry.reflects() {
+                (start, end) = (end, start);
+            }
+            puzzle_points(puzzle).find(|&other| {
+                let (other_start, other_end) = puzzle.point_side(other);
+                close(start, other_start) && close(end, other_end)
+            })
+        })
+        .collect()
+}
+
+/// every way of moving the board onto itself other than leaving it in place, as maps like `point_symmetry`,
+/// with whether the board is mirrored
+pub fn board_symmetries(puzzle: &impl PuzzleGeometry) -> Vec<(Vec<PointKey>, bool)> {
+    let generators = [
+        Symmetry::HalfTurn,
+        Symmetry::QuarterTurn,
+        Symmetry::MirrorHorizontal,
+        Symmetry::MirrorVertical,
+    ]
+    .into_iter()
+    .filter_map(|symmetry| Some((point_symmetry(puzzle, symmetry)?, symmetry.reflects())))
+    .collect::<Vec<_>>();
+    let identity = (puzzle_points(puzzle).collect::<Vec<_>>(), false);
+    // compose the symmetries the board has until no new ones come up
+    let mut symmetries = vec![identity];
+    let mut i = 0;
+    while i < symmetries.len() {
+        for (map, mirrors) in &generators {
+            let (first, first_mirrors) = &symmetries[i];
+            let composed = (
+                first.iter().map(|point| map[point.0]).collect::<Vec<_>>(),
+                first_mirrors != mirrors,
+            );
+            if !symmetries.contains(&composed) {
+                symmetries.push(composed);
+            }
+        }
+        i += 1;
+    }
+    symmetries.remove(0);
+    symmetries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::*;
+
+    #[test]
+    fn symmetries() {
+        let puzzle = SquarePuzzle::new(2, 3);
+        let point = |name| puzzle_find_point(&puzzle, name).unwrap();
+        let half_turn = point_symmetry(&puzzle, Symmetry::HalfTurn).unwrap();
+        assert_eq!(half_turn[point("a1>").0], point("b3<"));
+        let mirror = point_symmetry(&puzzle, Symmetry::MirrorHorizontal).unwrap();
+        assert_eq!(mirror[point("a1>").0], point("a3<"));
+        assert_eq!(mirror[point("a1v").0], point("a3v"));
+        let mirror = point_symmetry(&puzzle, Symmetry::MirrorVertical).unwrap();
+        assert_eq!(mirror[point("a2>").0], point("b2>"));
+        assert!(point_symmetry(&puzzle, Symmetry::QuarterTurn).is_none());
+
+        let puzzle = SquarePuzzle::new(3, 3);
+        let quarter_turn = point_symmetry(&puzzle, Symmetry::QuarterTurn).unwrap();
+        let a1 = puzzle_find_point(&puzzle, "a1>").unwrap();
+        assert_eq!(puzzle.format_point(quarter_turn[a1.0]), "a3v");
+    }
+
+    #[test]
+    fn all_symmetries() {
+        let mirrored = |puzzle: SquarePuzzle| {
+            let symmetries = board_symmetries(&puzzle);
+            let mirrored = symmetries.iter().filter(|(_, mirrors)| *mirrors).count();
+            (symmetries.len(), mirrored)
+        };
+        assert_eq!(mirrored(SquarePuzzle::new(2, 3)), (3, 2));
+        // the diagonal mirrors only come from composing a quarter turn with a mirror
+        assert_eq!(mirrored(SquarePuzzle::new(3, 3)), (7, 4));
+        assert_eq!(
+            mirrored(SquarePuzzle::parse_mask("##.\n###").unwrap()),
+            (0, 0)
+        );
+    }
+}
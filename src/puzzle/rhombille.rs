@@ -0,0 +1,68 @@
+use super::{TilingPuzzle, Vec2};
+
+impl TilingPuzzle {
+    /// rhombille tiling of `rows` x `cols` hexagons, each split into three rhombi from its center.
+    /// hexagons are pointy side up, with odd rows shifted half a hexagon to the right
+    pub fn rhombille(rows: usize, cols: usize) -> Self {
+        assert!(rows >= 1 && cols >= 1);
+        let width = 3f64.sqrt();
+        let mut polygons = vec![];
+        for row in 0..rows {
+            for col in 0..cols {
+                let shift = if row % 2 == 1 { 0.5 } else { 0.0 };
+                let center = Vec2::new(width * (col as f64 + shift), 1.5 * row as f64);
+                // corners clockwise from the top
+                let corner = |i: usize| {
+                    let angle = (-90.0 + 60.0 * i as f64).to_radians();
+                    center + Vec2::new(angle.cos(), angle.sin())
+                };
+                for i in [0, 2, 4] {
+                    polygons.push(vec![center, corner((i + 5) % 6), corner(i), corner(i + 1)]);
+                }
+            }
+        }
+        Self::from_polygons(polygons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::puzzle::tests::assert_well_defined;
+    use crate::puzzle::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn single_hexagon() {
+        let puzzle = TilingPuzzle::rhombille(1, 1);
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_pieces(), 3);
+        assert_eq!(puzzle.num_edges(), 3);
+        // all three rhombi are rotations of each other
+        let points = puzzle_points(&puzzle).collect_vec();
+        assert!(points.iter().all(|&a| points
+            .iter()
+            .filter(|&&b| puzzle.can_exchange(a, b))
+            .count()
+            == 3));
+    }
+
+    #[test]
+    fn well_defined() {
+        let puzzle = TilingPuzzle::rhombille(3, 3);
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_pieces(), 27);
+        // rhombi with no border sides can be turned half way around
+        let interior = puzzle_pieces(&puzzle)
+            .find(|&piece| {
+                puzzle.tiles()[piece.0]
+                    .neighbors
+                    .iter()
+                    .all(Option::is_some)
+            })
+            .unwrap();
+        let point = puzzle.arbitrary_point_on_piece(interior);
+        let opposite = puzzle.next_point_on_piece(puzzle.next_point_on_piece(point));
+        assert!(puzzle.can_exchange(point, opposite));
+        assert!(!puzzle.can_exchange(point, puzzle.next_point_on_piece(point)));
+    }
+}
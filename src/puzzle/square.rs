@@ -1,4 +1,4 @@
-use super::{EdgeKey, PieceKey, PointKey, Puzzle};
+use super::{cyclic_orbit, EdgeKey, PieceKey, PointKey, Puzzle, PuzzleGeometry, Vec2};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct PieceLoc {
@@ -9,59 +9,19 @@ impl PieceLoc {
     fn new(row: usize, col: usize) -> Self {
         Self { row, col }
     }
-    fn up_edge(&self) -> EdgeLoc {
-        EdgeLoc {
-            piece: PieceLoc {
-                row: self.row - 1,
-                col: self.col,
-            },
-            side: EdgeSide::Down,
-        }
-    }
-    fn right_edge(&self) -> EdgeLoc {
-        EdgeLoc {
-            piece: *self,
-            side: EdgeSide::Right,
-        }
-    }
-    fn down_edge(&self) -> EdgeLoc {
-        EdgeLoc {
-            piece: *self,
-            side: EdgeSide::Down,
-        }
-    }
-    fn left_edge(&self) -> EdgeLoc {
-        EdgeLoc {
-            piece: PieceLoc {
-                row: self.row,
-                col: self.col - 1,
-            },
-            side: EdgeSide::Right,
-        }
-    }
 
-    fn up_piece(&self) -> PieceLoc {
-        PieceLoc {
-            row: self.row - 1,
-            col: self.col,
-        }
-    }
-    fn right_piece(&self) -> PieceLoc {
-        PieceLoc {
-            row: self.row,
-            col: self.col + 1,
-        }
-    }
-    fn down_piece(&self) -> PieceLoc {
-        PieceLoc {
-            row: self.row + 1,
-            col: self.col,
-        }
-    }
-    fn left_piece(&self) -> PieceLoc {
-        PieceLoc {
-            row: self.row,
-            col: self.col - 1,
+    /// location of the neighboring cell, if it is within `rows` x `cols`
+    fn neighbor(&self, side: PointSide, rows: usize, cols: usize) -> Option<PieceLoc> {
+        let (row, col) = match side {
+            PointSide::Up => (self.row.checked_sub(1)?, self.col),
+            PointSide::Right => (self.row, self.col + 1),
+            PointSide::Down => (self.row + 1, self.col),
+            PointSide::Left => (self.row, self.col.checked_sub(1)?),
+        };
+        if row < rows && col < cols {
+            Some(PieceLoc::new(row, col))
+        } else {
+            None
         }
     }
 }
@@ -75,48 +35,33 @@ impl PointLoc {
     fn new(piece: PieceLoc, side: PointSide) -> Self {
         Self { piece, side }
     }
-    fn new_up(row: usize, col: usize) -> Self {
-        Self {
-            piece: PieceLoc::new(row, col),
-            side: PointSide::Up,
-        }
-    }
-    fn new_right(row: usize, col: usize) -> Self {
-        Self {
-            piece: PieceLoc::new(row, col),
-            side: PointSide::Right,
-        }
-    }
-    fn new_down(row: usize, col: usize) -> Self {
-        Self {
-            piece: PieceLoc::new(row, col),
-            side: PointSide::Down,
-        }
-    }
-    fn new_left(row: usize, col: usize) -> Self {
-        Self {
-            piece: PieceLoc::new(row, col),
-            side: PointSide::Left,
-        }
-    }
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum PointSide {
     Up,
     Right,
     Down,
     Left,
 }
-enum PieceLocationKind {
-    UpLeft,
-    Up,
-    UpRight,
-    Left,
-    Center,
-    Right,
-    DownLeft,
-    Down,
-    DownRight,
+impl PointSide {
+    /// in clockwise order
+    const ALL: [PointSide; 4] = [
+        PointSide::Up,
+        PointSide::Right,
+        PointSide::Down,
+        PointSide::Left,
+    ];
+    fn index(self) -> usize {
+        match self {
+            PointSide::Up => 0,
+            PointSide::Right => 1,
+            PointSide::Down => 2,
+            PointSide::Left => 3,
+        }
+    }
+    fn clockwise(self) -> PointSide {
+        PointSide::ALL[(self.index() + 1) % 4]
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -124,20 +69,6 @@ struct EdgeLoc {
     piece: PieceLoc,
     side: EdgeSide,
 }
-impl EdgeLoc {
-    fn new_right(row: usize, col: usize) -> Self {
-        Self {
-            piece: PieceLoc::new(row, col),
-            side: EdgeSide::Right,
-        }
-    }
-    fn new_down(row: usize, col: usize) -> Self {
-        Self {
-            piece: PieceLoc::new(row, col),
-            side: EdgeSide::Down,
-        }
-    }
-}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum EdgeSide {
@@ -145,243 +76,292 @@ enum EdgeSide {
     Down,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// a grid of square pieces, optionally with cells missing.
+/// missing cells (whether outside the outline or holes within it) are treated as border.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SquarePuzzle {
     rows: usize,
     cols: usize,
+    /// location of each `PieceKey`
+    pieces: Vec<PieceLoc>,
+    /// `PieceKey` of each cell in row major order, `None` if the cell is missing
+    cells: Vec<Option<PieceKey>>,
+    /// location of each `EdgeKey`, all right edges in row major order followed by all down edges
+    edges: Vec<EdgeLoc>,
+    /// `EdgeKey` of the right edge of each cell in row major order
+    right_edges: Vec<Option<EdgeKey>>,
+    /// `EdgeKey` of the down edge of each cell in row major order
+    down_edges: Vec<Option<EdgeKey>>,
+    /// exchange orbit of each `PointKey`
+    orbits: Vec<usize>,
+    /// orbit each `PointKey` can be exchanged into when its piece is turned over
+    flipped_orbits: Vec<Option<usize>>,
+    /// piece given its own orbit to break rotational symmetry
+    isolated: Option<PieceKey>,
 }
 impl SquarePuzzle {
     pub fn new(rows: usize, cols: usize) -> Self {
-        // rows or cols == 1 changes orbits, so is disallowed for simplicity
-        assert!(rows >= 2);
-        assert!(cols >= 2);
-        // rows + cols must be even to have an even number of edges
-        assert!((rows + cols) % 2 == 0);
-        Self { rows, cols }
-    }
-    fn num_vert_edges(&self) -> usize {
-        self.rows * (self.cols - 1)
-    }
-    fn num_horz_edges(&self) -> usize {
-        (self.rows - 1) * self.cols
-    }
-
-    fn last_col(&self) -> usize {
-        self.cols - 1
-    }
-    fn last_row(&self) -> usize {
-        self.rows - 1
-    }
-
-    fn piece_location_kind(&self, piece: PieceLoc) -> PieceLocationKind {
-        match (
-            piece.row == 0,
-            piece.row == self.last_row(),
-            piece.col == 0,
-            piece.col == self.last_col(),
-        ) {
-            (false, false, false, false) => PieceLocationKind::Center,
-            (true, false, false, false) => PieceLocationKind::Up,
-            (false, true, false, false) => PieceLocationKind::Down,
-            (false, false, true, false) => PieceLocationKind::Left,
-            (false, false, false, true) => PieceLocationKind::Right,
-            (true, false, true, false) => PieceLocationKind::UpLeft,
-            (true, false, false, true) => PieceLocationKind::UpRight,
-            (false, true, true, false) => PieceLocationKind::DownLeft,
-            (false, true, false, true) => PieceLocationKind::DownRight,
-            _ => unreachable!(),
+        let mut puzzle = Self::rectangle(rows, cols);
+        // up left corner is isolated to break rotational symmetry
+        puzzle.isolate_piece(PieceKey(0));
+        puzzle
+    }
+    /// a rectangle without an isolated piece, for searches that rule out turned copies of solutions themselves
+    pub fn rectangle(rows: usize, cols: usize) -> Self {
+        // a single piece has no edges
+        assert!(rows * cols >= 2);
+        Self::from_mask(&vec![vec![true; cols]; rows])
+            .expect("every cell of a rectangle has a neighbor")
+    }
+
+    /// parse a mask from text art, where `#` is a piece and `.` or ` ` is a missing cell.
+    /// ```text
+    /// ###.
+    /// ####
+    /// ```
+    pub fn parse_mask(art: &str) -> Result<Self, String> {
+        let mask = art
+            .trim_matches('\n')
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        '#' => Ok(true),
+                        '.' | ' ' => Ok(false),
+                        _ => Err(format!("unexpected character {c:?} in mask")),
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<bool>>, String>>()?;
+        Self::from_mask(&mask)
+    }
+
+    /// build a puzzle with a piece in every `true` cell of `mask`.
+    /// rows of `mask` may have different lengths, with the missing cells treated as `false`.
+    ///
+    /// unlike `new`, no piece is isolated, since an irregular outline generally has no rotational symmetry to break
+    pub fn from_mask(mask: &[Vec<bool>]) -> Result<Self, String> {
+        let rows = mask.len();
+        let cols = mask.iter().map(|row| row.len()).max().unwrap_or(0);
+        let in_mask = |loc: PieceLoc| mask[loc.row].get(loc.col).copied().unwrap_or(false);
+        let all_cells =
+            || (0..rows).flat_map(move |row| (0..cols).map(move |col| PieceLoc::new(row, col)));
+
+        let pieces = all_cells().filter(|&loc| in_mask(loc)).collect::<Vec<_>>();
+        let mut cells = vec![None; rows * cols];
+        for (i, loc) in pieces.iter().enumerate() {
+            cells[loc.row * cols + loc.col] = Some(PieceKey(i));
         }
+
+        let mut edges = vec![];
+        let mut right_edges = vec![None; rows * cols];
+        let mut down_edges = vec![None; rows * cols];
+        for (side, edge_side, edge_keys) in [
+            (PointSide::Right, EdgeSide::Right, &mut right_edges),
+            (PointSide::Down, EdgeSide::Down, &mut down_edges),
+        ] {
+            for &piece in &pieces {
+                let neighbor = piece.neighbor(side, rows, cols);
+                if neighbor.is_some_and(in_mask) {
+                    edge_keys[piece.row * cols + piece.col] = Some(EdgeKey(edges.len()));
+                    edges.push(EdgeLoc {
+                        piece,
+                        side: edge_side,
+                    });
+                }
+            }
+        }
+
+        let mut puzzle = Self {
+            rows,
+            cols,
+            pieces,
+            cells,
+            edges,
+            right_edges,
+            down_edges,
+            orbits: vec![],
+            flipped_orbits: vec![],
+            isolated: None,
+        };
+        if puzzle.num_pieces() == 0 {
+            return Err("mask has no pieces".to_owned());
+        }
+        for piece in &puzzle.pieces {
+            if !PointSide::ALL
+                .iter()
+                .any(|&side| puzzle.has_point(*piece, side))
+            {
+                return Err(format!(
+                    "piece {}{} of the mask has no neighbors",
+                    row_name(piece.row),
+                    col_name(piece.col)
+                ));
+            }
+        }
+
+        let shapes = (0..puzzle.num_points())
+            .map(|point| puzzle.point_shape(puzzle.point_loc(PointKey(point)), false))
+            .collect::<Vec<_>>();
+        puzzle.orbits = shapes
+            .iter()
+            .map(|shape| shapes.iter().position(|s| s == shape).unwrap())
+            .collect();
+        puzzle.flipped_orbits = (0..puzzle.num_points())
+            .map(|point| {
+                let shape = puzzle.point_shape(puzzle.point_loc(PointKey(point)), true);
+                shapes.iter().position(|s| s == &shape)
+            })
+            .collect();
+        Ok(puzzle)
     }
-    fn point_orbit(&self, point: PointLoc) -> u32 {
-        match (self.piece_location_kind(point.piece), point.side) {
-            // up left corner is isolated to break rotational symmetry
-            (PieceLocationKind::UpLeft, PointSide::Right) => 0,
-            (PieceLocationKind::UpLeft, PointSide::Down) => 1,
-            // corner
-            (PieceLocationKind::UpRight, PointSide::Down) => 2,
-            (PieceLocationKind::UpRight, PointSide::Left) => 3,
-            (PieceLocationKind::DownRight, PointSide::Left) => 2,
-            (PieceLocationKind::DownRight, PointSide::Up) => 3,
-            (PieceLocationKind::DownLeft, PointSide::Up) => 2,
-            (PieceLocationKind::DownLeft, PointSide::Right) => 3,
-            // edge
-            (PieceLocationKind::Up, PointSide::Right) => 4,
-            (PieceLocationKind::Up, PointSide::Down) => 5,
-            (PieceLocationKind::Up, PointSide::Left) => 6,
-            (PieceLocationKind::Right, PointSide::Down) => 4,
-            (PieceLocationKind::Right, PointSide::Left) => 5,
-            (PieceLocationKind::Right, PointSide::Up) => 6,
-            (PieceLocationKind::Down, PointSide::Left) => 4,
-            (PieceLocationKind::Down, PointSide::Up) => 5,
-            (PieceLocationKind::Down, PointSide::Right) => 6,
-            (PieceLocationKind::Left, PointSide::Up) => 4,
-            (PieceLocationKind::Left, PointSide::Right) => 5,
-            (PieceLocationKind::Left, PointSide::Down) => 6,
-            // center
-            (PieceLocationKind::Center, _) => 7,
-
-            _ => unreachable!(),
+
+    /// give every point of `piece` its own orbit, so that it cannot move
+    /// (turning it over in place is still allowed)
+    fn isolate_piece(&mut self, piece: PieceKey) {
+        let start = self.arbitrary_point_on_piece(piece);
+        let mut points = vec![start];
+        while self.next_point_on_piece(*points.last().unwrap()) != start {
+            points.push(self.next_point_on_piece(*points.last().unwrap()));
+        }
+        let num_points = self.orbits.len();
+        let isolated = |point: PointKey| num_points + point.0;
+        let flipped_orbits = points
+            .iter()
+            .map(|point| {
+                points
+                    .iter()
+                    .find(|other| Some(self.orbits[other.0]) == self.flipped_orbits[point.0])
+                    .map(|&other| isolated(other))
+            })
+            .collect::<Vec<_>>();
+        for (&point, flipped_orbit) in points.iter().zip(flipped_orbits) {
+            self.orbits[point.0] = isolated(point);
+            self.flipped_orbits[point.0] = flipped_orbit;
         }
+        self.isolated = Some(piece);
     }
 
-    fn piece_loc(&self, piece: PieceKey) -> PieceLoc {
-        assert!(piece.0 < self.num_pieces());
-        let row = piece.0 / self.cols;
-        let col = piece.0 % self.cols;
-        PieceLoc::new(row, col)
+    fn cell(&self, piece: PieceLoc) -> Option<PieceKey> {
+        self.cells[piece.row * self.cols + piece.col]
+    }
+    fn has_point(&self, piece: PieceLoc, side: PointSide) -> bool {
+        piece
+            .neighbor(side, self.rows, self.cols)
+            .is_some_and(|neighbor| self.cell(neighbor).is_some())
+    }
+    /// which sides of the piece have points and which are flat, up to rotation.
+    /// `flipped` lists the sides counter-clockwise, as seen from the back of the piece
+    fn point_shape(&self, point: PointLoc, flipped: bool) -> (Vec<bool>, usize) {
+        let mut sides = PointSide::ALL.map(|side| self.has_point(point.piece, side));
+        let mut side = point.side.index();
+        if flipped {
+            sides.reverse();
+            side = 3 - side;
+        }
+        cyclic_orbit(&sides, side)
     }
-    fn piece_key(&self, piece: PieceLoc) -> PieceKey {
-        PieceKey(self.cols * piece.row + piece.col)
+
+    fn piece_loc(&self, piece: PieceKey) -> PieceLoc {
+        self.pieces[piece.0]
     }
     fn point_loc(&self, point: PointKey) -> PointLoc {
-        let mut index = point.0;
-        assert!(index < self.num_points());
-        let first: bool = index & 1 == 0;
-        index >>= 1;
-        if index < self.num_vert_edges() {
-            let row = index / (self.cols - 1);
-            let col = index % (self.cols - 1);
-            if first {
-                PointLoc::new_right(row, col)
-            } else {
-                PointLoc::new_left(row, col + 1)
-            }
-        } else {
-            index -= self.num_vert_edges();
-            let row = index / self.cols;
-            let col = index % self.cols;
-            if first {
-                PointLoc::new_down(row, col)
-            } else {
-                PointLoc::new_up(row + 1, col)
-            }
-        }
-    }
-    fn point_key(&self, point: PointLoc) -> PointKey {
-        match point.side {
-            PointSide::Right => PointKey((point.piece.row * (self.cols - 1) + point.piece.col) * 2),
-            PointSide::Left => {
-                PointKey((point.piece.row * (self.cols - 1) + (point.piece.col - 1)) * 2 + 1)
-            }
-            PointSide::Down => PointKey(
-                (self.num_vert_edges() + point.piece.row * self.cols + point.piece.col) * 2,
+        let edge = self.edges[point.0 >> 1];
+        let first = point.0 & 1 == 0;
+        match (edge.side, first) {
+            (EdgeSide::Right, true) => PointLoc::new(edge.piece, PointSide::Right),
+            (EdgeSide::Right, false) => PointLoc::new(
+                PieceLoc::new(edge.piece.row, edge.piece.col + 1),
+                PointSide::Left,
             ),
-            PointSide::Up => PointKey(
-                (self.num_vert_edges() + (point.piece.row - 1) * self.cols + point.piece.col) * 2
-                    + 1,
+            (EdgeSide::Down, true) => PointLoc::new(edge.piece, PointSide::Down),
+            (EdgeSide::Down, false) => PointLoc::new(
+                PieceLoc::new(edge.piece.row + 1, edge.piece.col),
+                PointSide::Up,
             ),
         }
     }
-    fn edge_loc(&self, edge: EdgeKey) -> EdgeLoc {
-        assert!(edge.0 < self.num_edges());
-        if edge.0 < self.num_vert_edges() {
-            let row = edge.0 / (self.cols - 1);
-            let col = edge.0 % (self.cols - 1);
-            EdgeLoc::new_right(row, col)
-        } else {
-            let row = (edge.0 - self.num_vert_edges()) / self.cols;
-            let col = (edge.0 - self.num_vert_edges()) % self.cols;
-            EdgeLoc::new_down(row, col)
-        }
+    fn point_key(&self, point: PointLoc) -> PointKey {
+        let (edge, first) = match point.side {
+            PointSide::Right => (point.piece, true),
+            PointSide::Down => (point.piece, true),
+            PointSide::Left => (PieceLoc::new(point.piece.row, point.piece.col - 1), false),
+            PointSide::Up => (PieceLoc::new(point.piece.row - 1, point.piece.col), false),
+        };
+        let edge_keys = match point.side {
+            PointSide::Right | PointSide::Left => &self.right_edges,
+            PointSide::Down | PointSide::Up => &self.down_edges,
+        };
+        let edge = edge_keys[edge.row * self.cols + edge.col].expect("point is on the border");
+        PointKey(edge.0 * 2 + if first { 0 } else { 1 })
     }
-    fn edge_key(&self, edge: EdgeLoc) -> EdgeKey {
-        match edge.side {
-            EdgeSide::Right => EdgeKey(edge.piece.row * (self.cols - 1) + edge.piece.col),
-            EdgeSide::Down => {
-                EdgeKey(self.num_vert_edges() + edge.piece.row * self.cols + edge.piece.col)
-            }
-        }
+    fn edge_loc(&self, edge: EdgeKey) -> EdgeLoc {
+        self.edges[edge.0]
     }
 }
 impl Puzzle for SquarePuzzle {
     fn num_pieces(&self) -> usize {
-        self.rows * self.cols
+        self.pieces.len()
     }
     fn num_edges(&self) -> usize {
-        self.num_vert_edges() + self.num_horz_edges()
+        self.edges.len()
     }
 
     fn arbitrary_point_on_piece(&self, piece: PieceKey) -> PointKey {
         let piece = self.piece_loc(piece);
-        let side = if piece.row == 0 {
-            PointSide::Down
-        } else {
-            PointSide::Up
-        };
+        let side = [
+            PointSide::Up,
+            PointSide::Down,
+            PointSide::Left,
+            PointSide::Right,
+        ]
+        .into_iter()
+        .find(|&side| self.has_point(piece, side))
+        .unwrap();
         self.point_key(PointLoc::new(piece, side))
     }
     fn next_point_on_piece(&self, point: PointKey) -> PointKey {
         let point = self.point_loc(point);
-        let next_side = match (self.piece_location_kind(point.piece), point.side) {
-            // corner
-            (PieceLocationKind::UpLeft, PointSide::Right) => PointSide::Down,
-            (PieceLocationKind::UpLeft, PointSide::Down) => PointSide::Right,
-            (PieceLocationKind::UpRight, PointSide::Down) => PointSide::Left,
-            (PieceLocationKind::UpRight, PointSide::Left) => PointSide::Down,
-            (PieceLocationKind::DownRight, PointSide::Left) => PointSide::Up,
-            (PieceLocationKind::DownRight, PointSide::Up) => PointSide::Left,
-            (PieceLocationKind::DownLeft, PointSide::Up) => PointSide::Right,
-            (PieceLocationKind::DownLeft, PointSide::Right) => PointSide::Up,
-            // edge
-            (PieceLocationKind::Up, PointSide::Right) => PointSide::Down,
-            (PieceLocationKind::Up, PointSide::Down) => PointSide::Left,
-            (PieceLocationKind::Up, PointSide::Left) => PointSide::Right,
-            (PieceLocationKind::Right, PointSide::Down) => PointSide::Left,
-            (PieceLocationKind::Right, PointSide::Left) => PointSide::Up,
-            (PieceLocationKind::Right, PointSide::Up) => PointSide::Down,
-            (PieceLocationKind::Down, PointSide::Left) => PointSide::Up,
-            (PieceLocationKind::Down, PointSide::Up) => PointSide::Right,
-            (PieceLocationKind::Down, PointSide::Right) => PointSide::Left,
-            (PieceLocationKind::Left, PointSide::Up) => PointSide::Right,
-            (PieceLocationKind::Left, PointSide::Right) => PointSide::Down,
-            (PieceLocationKind::Left, PointSide::Down) => PointSide::Up,
-            // center
-            (PieceLocationKind::Center, PointSide::Up) => PointSide::Right,
-            (PieceLocationKind::Center, PointSide::Right) => PointSide::Down,
-            (PieceLocationKind::Center, PointSide::Down) => PointSide::Left,
-            (PieceLocationKind::Center, PointSide::Left) => PointSide::Up,
-
-            _ => unreachable!(),
-        };
+        let mut next_side = point.side.clockwise();
+        while !self.has_point(point.piece, next_side) {
+            next_side = next_side.clockwise();
+        }
         self.point_key(PointLoc::new(point.piece, next_side))
     }
     fn point_piece(&self, point: PointKey) -> PieceKey {
-        self.piece_key(self.point_loc(point).piece)
+        self.cell(self.point_loc(point).piece).unwrap()
     }
 
     fn arbitrary_point_on_edge(&self, edge: EdgeKey) -> PointKey {
-        let edge = self.edge_loc(edge);
-        let side = match edge.side {
-            EdgeSide::Right => PointSide::Right,
-            EdgeSide::Down => PointSide::Down,
-        };
-        self.point_key(PointLoc::new(edge.piece, side))
+        PointKey(edge.0 * 2)
     }
     fn other_point_on_edge(&self, point: PointKey) -> PointKey {
-        let point = self.point_loc(point);
-        let other_point = match point.side {
-            PointSide::Up => PointLoc::new_down(point.piece.row - 1, point.piece.col),
-            PointSide::Right => PointLoc::new_left(point.piece.row, point.piece.col + 1),
-            PointSide::Down => PointLoc::new_up(point.piece.row + 1, point.piece.col),
-            PointSide::Left => PointLoc::new_right(point.piece.row, point.piece.col - 1),
-        };
-        self.point_key(other_point)
+        PointKey(point.0 ^ 1)
     }
     fn point_edge(&self, point: PointKey) -> EdgeKey {
-        let point = self.point_loc(point);
-        let edge = match point.side {
-            PointSide::Up => EdgeLoc::new_down(point.piece.row - 1, point.piece.col),
-            PointSide::Right => EdgeLoc::new_right(point.piece.row, point.piece.col),
-            PointSide::Down => EdgeLoc::new_down(point.piece.row, point.piece.col),
-            PointSide::Left => EdgeLoc::new_right(point.piece.row, point.piece.col - 1),
-        };
-        self.edge_key(edge)
+        EdgeKey(point.0 >> 1)
     }
 
     fn can_exchange(&self, point_a: PointKey, point_b: PointKey) -> bool {
-        let point_a = self.point_loc(point_a);
-        let point_b = self.point_loc(point_b);
-        self.point_orbit(point_a) == self.point_orbit(point_b)
+        self.orbits[point_a.0] == self.orbits[point_b.0]
+    }
+    fn can_exchange_flipped(&self, point_a: PointKey, point_b: PointKey) -> bool {
+        self.flipped_orbits[point_a.0] == Some(self.orbits[point_b.0])
+    }
+    /// isolated pieces can only be exchanged with each other, if both boards have one
+    fn can_exchange_into(
+        &self,
+        point_a: PointKey,
+        other: &Self,
+        point_b: PointKey,
+        flipped: bool,
+    ) -> bool {
+        let pinned = self.isolated.is_some() && other.isolated.is_some();
+        let isolated_a = Some(self.point_piece(point_a)) == self.isolated;
+        let isolated_b = Some(other.point_piece(point_b)) == other.isolated;
+        (!pinned || isolated_a == isolated_b)
+            && self.point_shape(self.point_loc(point_a), flipped)
+                == other.point_shape(other.point_loc(point_b), false)
     }
 
     fn format_edge(&self, edge: EdgeKey) -> String {
@@ -392,11 +372,15 @@ impl Puzzle for SquarePuzzle {
         };
         format!(
             "{}{}{}",
-            row_char(edge.piece.row),
-            col_char(edge.piece.col),
+            row_name(edge.piece.row),
+            col_name(edge.piece.col),
             side_char
         )
     }
+    fn format_piece(&self, piece: PieceKey) -> String {
+        let piece = self.piece_loc(piece);
+        format!("{}{}", row_name(piece.row), col_name(piece.col))
+    }
     fn format_point(&self, point: PointKey) -> String {
         let point = self.point_loc(point);
         let side_char = match point.side {
@@ -407,19 +391,223 @@ impl Puzzle for SquarePuzzle {
         };
         format!(
             "{}{}{}",
-            row_char(point.piece.row),
-            col_char(point.piece.col),
+            row_name(point.piece.row),
+            col_name(point.piece.col),
             side_char
         )
     }
 }
-fn row_char(row: usize) -> char {
-    assert!(row < 26);
-    ('a' as u8 + row as u8) as char
+impl PuzzleGeometry for SquarePuzzle {
+    fn piece_corners(&self, piece: PieceKey) -> Vec<Vec2> {
+        let piece = self.piece_loc(piece);
+        let (x, y) = (piece.col as f64, piece.row as f64);
+        vec![
+            Vec2::new(x, y),
+            Vec2::new(x + 1.0, y),
+            Vec2::new(x + 1.0, y + 1.0),
+            Vec2::new(x, y + 1.0),
+        ]
+    }
+    fn point_side(&self, point: PointKey) -> (Vec2, Vec2) {
+        let point = self.point_loc(point);
+        // sides are in the same order as the corners
+        let corners = self.piece_corners(self.cell(point.piece).unwrap());
+        let side = point.side.index();
+        (corners[side], corners[(side + 1) % 4])
+    }
 }
-fn col_char(col: usize) -> char {
-    assert!(col < 9);
-    ('1' as u8 + col as u8) as char
+
+/// `a` to `z`, then `aa`, `ab` and so on
+fn row_name(row: usize) -> String {
+    let letter = (b'a' + (row % 26) as u8) as char;
+    match row / 26 {
+        0 => letter.to_string(),
+        prefix => format!("{}{letter}", row_name(prefix - 1)),
+    }
 }
+/// numbered from 1, so names are letters then digits and tell rows and columns apart at any size
+fn col_name(col: usize) -> String {
+    (col + 1).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::tests::assert_well_defined;
+    use crate::puzzle::*;
+    use itertools::Itertools;
+
+    fn num_orbits(puzzle: &SquarePuzzle) -> usize {
+        puzzle.orbits.iter().unique().count()
+    }
+
+    #[test]
+    fn rectangle() {
+        let puzzle = SquarePuzzle::new(3, 5);
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_pieces(), 15);
+        assert_eq!(puzzle.num_edges(), 22);
+        // isolated corner, corner, edge, center
+        assert_eq!(num_orbits(&puzzle), 2 + 2 + 3 + 1);
+        assert_eq!(num_orbits(&SquarePuzzle::rectangle(3, 5)), 2 + 3 + 1);
+    }
+
+    #[test]
+    fn strip() {
+        let puzzle = SquarePuzzle::new(1, 4);
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_edges(), 3);
+        // isolated end, other end, middle
+        assert_eq!(num_orbits(&puzzle), 1 + 1 + 1);
+        let middle_left = puzzle.point_key(PointLoc::new(PieceLoc::new(0, 1), PointSide::Left));
+        let middle_right = puzzle.point_key(PointLoc::new(PieceLoc::new(0, 2), PointSide::Right));
+        assert!(puzzle.can_exchange(middle_left, middle_right));
+    }
+
+    #[test]
+    fn flipped() {
+        let puzzle = SquarePuzzle::new(3, 3);
+        let point = |row, col, side| puzzle.point_key(PointLoc::new(PieceLoc::new(row, col), side));
+        // an edge piece turned over swaps its left and right
+        assert!(puzzle
+            .can_exchange_flipped(point(0, 1, PointSide::Left), point(0, 1, PointSide::Right)));
+        assert!(
+            puzzle.can_exchange_flipped(point(0, 1, PointSide::Left), point(1, 0, PointSide::Up))
+        );
+        assert!(!puzzle
+            .can_exchange_flipped(point(0, 1, PointSide::Left), point(0, 1, PointSide::Down)));
+        // the isolated corner can only be turned over in place
+        assert!(puzzle
+            .can_exchange_flipped(point(0, 0, PointSide::Right), point(0, 0, PointSide::Down)));
+        assert!(!puzzle
+            .can_exchange_flipped(point(0, 0, PointSide::Right), point(0, 2, PointSide::Left)));
+        assert!(
+            puzzle.can_exchange_flipped(point(0, 2, PointSide::Left), point(2, 2, PointSide::Left))
+        );
+    }
+
+    #[test]
+    fn different_outline() {
+        let wide = SquarePuzzle::new(2, 3);
+        let tall = SquarePuzzle::new(3, 2);
+        let point = |puzzle: &SquarePuzzle, row, col, side| {
+            puzzle.point_key(PointLoc::new(PieceLoc::new(row, col), side))
+        };
+        // the middle of a long side goes to the middle of the other board's long side
+        let top_middle = point(&wide, 0, 1, PointSide::Down);
+        assert!(wide.can_exchange_into(
+            top_middle,
+            &tall,
+            point(&tall, 1, 0, PointSide::Right),
+            false
+        ));
+        assert!(!wide.can_exchange_into(
+            top_middle,
+            &tall,
+            point(&tall, 1, 0, PointSide::Up),
+            false
+        ));
+        // isolated corners only go to each other
+        let corner = point(&wide, 0, 0, PointSide::Right);
+        assert!(wide.can_exchange_into(corner, &tall, point(&tall, 0, 0, PointSide::Right), false));
+        assert!(!wide.can_exchange_into(corner, &tall, point(&tall, 2, 1, PointSide::Left), false));
+        // unless the other board has no isolated piece
+        let mask = SquarePuzzle::parse_mask("##\n##\n##").unwrap();
+        assert!(wide.can_exchange_into(corner, &mask, point(&mask, 2, 1, PointSide::Left), false));
+    }
 
-// TODO tests!
+    #[test]
+    fn rectangle_keys() {
+        let puzzle = SquarePuzzle::new(2, 4);
+        let points = puzzle_points(&puzzle)
+            .map(|point| puzzle.format_point(point))
+            .join(" ");
+        assert_eq!(
+            points,
+            "a1> a2< a2> a3< a3> a4< b1> b2< b2> b3< b3> b4< a1v b1^ a2v b2^ a3v b3^ a4v b4^"
+        );
+    }
+
+    #[test]
+    fn mask_with_hole() {
+        let puzzle = SquarePuzzle::parse_mask(
+            "
+###
+#.#
+###
+",
+        )
+        .unwrap();
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_pieces(), 8);
+        assert_eq!(puzzle.num_edges(), 8);
+        // corners have flat up and left, edges have flat up and down
+        assert_eq!(num_orbits(&puzzle), 2 + 1);
+    }
+
+    #[test]
+    fn mask_irregular() {
+        let puzzle = SquarePuzzle::parse_mask("###.\n####").unwrap();
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_pieces(), 7);
+        assert_eq!(puzzle.num_edges(), 8);
+        // a3 has two adjacent flat sides like the corners
+        assert!(puzzle.can_exchange(
+            puzzle.point_key(PointLoc::new(PieceLoc::new(0, 2), PointSide::Down)),
+            puzzle.point_key(PointLoc::new(PieceLoc::new(0, 0), PointSide::Right)),
+        ));
+        // b3 has a single flat side like a2
+        assert!(puzzle.can_exchange(
+            puzzle.point_key(PointLoc::new(PieceLoc::new(1, 2), PointSide::Up)),
+            puzzle.point_key(PointLoc::new(PieceLoc::new(0, 1), PointSide::Down)),
+        ));
+    }
+
+    #[test]
+    fn mask_opposite_flat_sides() {
+        let puzzle = SquarePuzzle::parse_mask("#####").unwrap();
+        assert_well_defined(&puzzle);
+        // both points of a middle piece are in the same orbit, since the piece can be turned around
+        let right = puzzle.point_key(PointLoc::new(PieceLoc::new(0, 1), PointSide::Right));
+        let left = puzzle.point_key(PointLoc::new(PieceLoc::new(0, 1), PointSide::Left));
+        assert!(puzzle.can_exchange(right, left));
+        // end pieces have three flat sides
+        let end = puzzle.point_key(PointLoc::new(PieceLoc::new(0, 0), PointSide::Right));
+        assert!(!puzzle.can_exchange(right, end));
+        assert_eq!(num_orbits(&puzzle), 2);
+    }
+
+    #[test]
+    fn mask_errors() {
+        assert_eq!(
+            SquarePuzzle::parse_mask("##.#").err().as_deref(),
+            Some("piece a4 of the mask has no neighbors")
+        );
+        assert_eq!(
+            SquarePuzzle::parse_mask("#x#").err().as_deref(),
+            Some("unexpected character 'x' in mask")
+        );
+        assert!(SquarePuzzle::parse_mask("..\n..").is_err());
+    }
+
+    #[test]
+    fn long_names() {
+        assert_eq!(row_name(25), "z");
+        assert_eq!(row_name(26), "aa");
+        assert_eq!(row_name(27 * 26), "aaa");
+        let wide = SquarePuzzle::rectangle(1, 12);
+        assert_eq!(wide.format_piece(PieceKey(11)), "a12");
+        let names = puzzle_points(&wide)
+            .map(|point| wide.format_point(point))
+            .collect_vec();
+        assert!(names.iter().all_unique());
+        for point in puzzle_points(&wide) {
+            assert_eq!(
+                puzzle_find_point(&wide, &wide.format_point(point)),
+                Some(point)
+            );
+        }
+        let tall = SquarePuzzle::rectangle(30, 1);
+        assert_eq!(tall.format_piece(PieceKey(29)), "ad1");
+    }
+}
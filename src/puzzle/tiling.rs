@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::{area, cyclic_orbit, EdgeKey, PieceKey, PointKey, Puzzle, PuzzleGeometry, Vec2};
+
+/// a polygonal piece of a `TilingPuzzle`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    /// corners in clockwise order (with y pointing down),
+    /// side `i` goes from `corners[i]` to `corners[i + 1]`
+    pub corners: Vec<Vec2>,
+    /// index of the tile across each side, `None` for the border
+    pub neighbors: Vec<Option<usize>>,
+    /// tiles can only be exchanged if the kinds of their sides match up to rotation
+    pub side_kinds: Vec<u32>,
+    /// kind of each side when the tile is turned over,
+    /// so a turned over tile can be exchanged if these match the other tile's `side_kinds` in reverse
+    pub mirror_side_kinds: Vec<u32>,
+}
+
+/// a puzzle made of arbitrary polygonal pieces.
+/// any two tiles may share at most one side
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilingPuzzle {
+    tiles: Vec<Tile>,
+    /// tile and side of each `PointKey`, the first point of each edge is on the lower tile
+    points: Vec<(usize, usize)>,
+    /// `PointKey` of each side of each tile, `None` for the border
+    side_points: Vec<Vec<Option<PointKey>>>,
+    /// exchange orbit of each `PointKey`
+    orbits: Vec<usize>,
+    /// orbit each `PointKey` can be exchanged into when its tile is turned over
+    flipped_orbits: Vec<Option<usize>>,
+}
+impl TilingPuzzle {
+    pub fn new(tiles: Vec<Tile>) -> Self {
+        let mut points = vec![];
+        let mut side_points = tiles
+            .iter()
+            .map(|tile| vec![None; tile.corners.len()])
+            .collect::<Vec<_>>();
+        for (a, tile) in tiles.iter().enumerate() {
+            assert_eq!(tile.neighbors.len(), tile.corners.len());
+            assert_eq!(tile.side_kinds.len(), tile.corners.len());
+            assert_eq!(tile.mirror_side_kinds.len(), tile.corners.len());
+            assert!(area(&tile.corners) > 0.0, "tile {a} is not clockwise");
+            assert!(
+                tile.neighbors.iter().any(Option::is_some),
+                "tile {a} has no neighbors"
+            );
+            for (side_a, &neighbor) in tile.neighbors.iter().enumerate() {
+                let Some(b) = neighbor.filter(|&b| b > a) else {
+                    continue;
+                };
+                let sides_b = tiles[b]
+                    .neighbors
+                    .iter()
+                    .positions(|&n| n == Some(a))
+                    .collect::<Vec<_>>();
+                assert!(
+                    sides_b.len() == 1,
+                    "tiles {a} and {b} must share exactly one side"
+                );
+                side_points[a][side_a] = Some(PointKey(points.len()));
+                points.push((a, side_a));
+                side_points[b][sides_b[0]] = Some(PointKey(points.len()));
+                points.push((b, sides_b[0]));
+            }
+        }
+
+        let mut puzzle = Self {
+            tiles,
+            points,
+            side_points,
+            orbits: vec![],
+            flipped_orbits: vec![],
+        };
+        let shapes = (0..puzzle.num_points())
+            .map(|point| puzzle.point_shape(PointKey(point), false))
+            .collect::<Vec<_>>();
+        puzzle.orbits = shapes
+            .iter()
+            .map(|shape| shapes.iter().position(|s| s == shape).unwrap())
+            .collect();
+        puzzle.flipped_orbits = (0..puzzle.num_points())
+            .map(|point| {
+                let shape = puzzle.point_shape(PointKey(point), true);
+                shapes.iter().position(|s| s == &shape)
+            })
+            .collect();
+        puzzle
+    }
+
+    /// tiles from polygons that share sides where they touch.
+    /// tiles can be exchanged if they are congruent without reflection
+    pub fn from_polygons(polygons: Vec<Vec<Vec2>>) -> Self {
+        // round so that shared corners compare equal
+        let key = |p: Vec2| ((p.x * 1e6).round() as i64, (p.y * 1e6).round() as i64);
+        let polygons = polygons
+            .into_iter()
+            .map(|mut corners| {
+                if area(&corners) < 0.0 {
+                    corners.reverse();
+                }
+                corners
+            })
+            .collect::<Vec<_>>();
+        let sides = polygons
+            .iter()
+            .enumerate()
+            .flat_map(|(tile, corners)| {
+                (0..corners.len()).map(move |side| {
+                    let next = corners[(side + 1) % corners.len()];
+                    ((key(corners[side]), key(next)), tile)
+                })
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut kinds = vec![];
+        let tiles = polygons
+            .into_iter()
+            .map(|corners| {
+                let n = corners.len();
+                let neighbors = (0..n)
+                    .map(|side| {
+                        let next = corners[(side + 1) % n];
+                        sides.get(&(key(next), key(corners[side]))).copied()
+                    })
+                    .collect();
+                let turns = (0..n)
+                    .map(|corner| {
+                        let prev = corners[(corner + n - 1) % n];
+                        let next = corners[(corner + 1) % n];
+                        let (d_in, d_out) = (corners[corner] - prev, next - corners[corner]);
+                        d_in.cross(d_out).atan2(d_in.dot(d_out))
+                    })
+                    .collect::<Vec<_>>();
+                let mut kind = |side: usize, turn: f64| {
+                    let length = (corners[(side + 1) % n] - corners[side]).length();
+                    let shape = ((length * 1e6).round() as i64, (turn * 1e6).round() as i64);
+                    let kind = kinds.iter().position(|&k| k == shape).unwrap_or_else(|| {
+                        kinds.push(shape);
+                        kinds.len() - 1
+                    });
+                    kind as u32
+                };
+                // a side is identified by its length and the turn at its start,
+                // which is the turn at its end when the tile is turned over
+                let side_kinds = (0..n).map(|side| kind(side, turns[side])).collect();
+                let mirror_side_kinds = (0..n)
+                    .map(|side| kind(side, turns[(side + 1) % n]))
+                    .collect();
+                Tile {
+                    corners,
+                    neighbors,
+                    side_kinds,
+                    mirror_side_kinds,
+                }
+            })
+            .collect();
+        Self::new(tiles)
+    }
+
+    /// kinds of the tile's sides and which have neighbors, up to rotation.
+    /// `flipped` lists the sides counter-clockwise, as seen from the back of the tile
+    fn point_shape(&self, point: PointKey, flipped: bool) -> (Vec<(u32, bool)>, usize) {
+        let (tile, mut side) = self.points[point.0];
+        let tile = &self.tiles[tile];
+        let kinds = if flipped {
+            &tile.mirror_side_kinds
+        } else {
+            &tile.side_kinds
+        };
+        let mut sides = kinds
+            .iter()
+            .zip(&tile.neighbors)
+            .map(|(&kind, neighbor)| (kind, neighbor.is_some()))
+            .collect::<Vec<_>>();
+        if flipped {
+            sides.reverse();
+            side = sides.len() - 1 - side;
+        }
+        cyclic_orbit(&sides, side)
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+}
+impl Puzzle for TilingPuzzle {
+    fn num_pieces(&self) -> usize {
+        self.tiles.len()
+    }
+    fn num_edges(&self) -> usize {
+        self.points.len() / 2
+    }
+
+    fn arbitrary_point_on_piece(&self, piece: PieceKey) -> PointKey {
+        self.side_points[piece.0]
+            .iter()
+            .flatten()
+            .copied()
+            .next()
+            .unwrap()
+    }
+    fn next_point_on_piece(&self, point: PointKey) -> PointKey {
+        let (tile, side) = self.points[point.0];
+        let sides = &self.side_points[tile];
+        (1..=sides.len())
+            .find_map(|i| sides[(side + i) % sides.len()])
+            .unwrap()
+    }
+    fn point_piece(&self, point: PointKey) -> PieceKey {
+        PieceKey(self.points[point.0].0)
+    }
+
+    fn arbitrary_point_on_edge(&self, edge: EdgeKey) -> PointKey {
+        PointKey(edge.0 * 2)
+    }
+    fn other_point_on_edge(&self, point: PointKey) -> PointKey {
+        PointKey(point.0 ^ 1)
+    }
+    fn point_edge(&self, point: PointKey) -> EdgeKey {
+        EdgeKey(point.0 >> 1)
+    }
+
+    fn can_exchange(&self, point_a: PointKey, point_b: PointKey) -> bool {
+        self.orbits[point_a.0] == self.orbits[point_b.0]
+    }
+    fn can_exchange_flipped(&self, point_a: PointKey, point_b: PointKey) -> bool {
+        self.flipped_orbits[point_a.0] == Some(self.orbits[point_b.0])
+    }
+    /// side kinds are compared as numbers, so they must mean the same in both tilings
+    fn can_exchange_into(
+        &self,
+        point_a: PointKey,
+        other: &Self,
+        point_b: PointKey,
+        flipped: bool,
+    ) -> bool {
+        self.point_shape(point_a, flipped) == other.point_shape(point_b, false)
+    }
+
+    fn format_piece(&self, piece: PieceKey) -> String {
+        piece.0.to_string()
+    }
+    fn format_point(&self, point: PointKey) -> String {
+        let (tile, side) = self.points[point.0];
+        let neighbor = self.tiles[tile].neighbors[side].unwrap();
+        format!("{tile}>{neighbor}")
+    }
+    fn format_edge(&self, edge: EdgeKey) -> String {
+        let (tile, side) = self.points[edge.0 * 2];
+        let neighbor = self.tiles[tile].neighbors[side].unwrap();
+        format!("{tile}|{neighbor}")
+    }
+}
+impl PuzzleGeometry for TilingPuzzle {
+    fn piece_corners(&self, piece: PieceKey) -> Vec<Vec2> {
+        self.tiles[piece.0].corners.clone()
+    }
+    fn point_side(&self, point: PointKey) -> (Vec2, Vec2) {
+        let (tile, side) = self.points[point.0];
+        let corners = &self.tiles[tile].corners;
+        (corners[side], corners[(side + 1) % corners.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::tests::assert_well_defined;
+    use crate::puzzle::*;
+    use itertools::Itertools;
+
+    /// 2x2 grid of unit squares, numbered in row major order
+    fn square_tiles() -> Vec<Tile> {
+        let square = |row: usize, col: usize, neighbors: [Option<usize>; 4]| {
+            let (x, y) = (col as f64, row as f64);
+            Tile {
+                corners: vec![
+                    Vec2::new(x, y),
+                    Vec2::new(x + 1.0, y),
+                    Vec2::new(x + 1.0, y + 1.0),
+                    Vec2::new(x, y + 1.0),
+                ],
+                neighbors: neighbors.to_vec(),
+                side_kinds: vec![0; 4],
+                mirror_side_kinds: vec![0; 4],
+            }
+        };
+        vec![
+            square(0, 0, [None, Some(1), Some(2), None]),
+            square(0, 1, [None, None, Some(3), Some(0)]),
+            square(1, 0, [Some(0), Some(3), None, None]),
+            square(1, 1, [Some(1), None, None, Some(2)]),
+        ]
+    }
+
+    #[test]
+    fn squares() {
+        let puzzle = TilingPuzzle::new(square_tiles());
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_pieces(), 4);
+        assert_eq!(puzzle.num_edges(), 4);
+        // every tile is a corner, with two points that can't be exchanged with each other
+        assert_eq!(puzzle.orbits.iter().unique().count(), 2);
+        assert_eq!(puzzle.format_point(PointKey(0)), "0>1");
+        assert_eq!(puzzle.format_edge(EdgeKey(3)), "2|3");
+    }
+
+    #[test]
+    fn side_kinds() {
+        let mut tiles = square_tiles();
+        tiles[3].side_kinds = vec![1, 0, 0, 0];
+        tiles[3].mirror_side_kinds = vec![1, 0, 0, 0];
+        let puzzle = TilingPuzzle::new(tiles);
+        assert_well_defined(&puzzle);
+        let point_0 = puzzle.arbitrary_point_on_piece(PieceKey(0));
+        let point_3 = puzzle.arbitrary_point_on_piece(PieceKey(3));
+        assert!(!puzzle.can_exchange(point_0, point_3));
+        assert!(!puzzle.can_exchange(point_0, puzzle.next_point_on_piece(point_3)));
+    }
+
+    #[test]
+    fn from_polygons() {
+        let polygons = square_tiles()
+            .into_iter()
+            .map(|tile| tile.corners)
+            .collect::<Vec<_>>();
+        let puzzle = TilingPuzzle::from_polygons(polygons.clone());
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.tiles, square_tiles());
+
+        // counter-clockwise polygons are reversed
+        let reversed = polygons
+            .into_iter()
+            .map(|corners| corners.into_iter().rev().collect())
+            .collect();
+        let puzzle = TilingPuzzle::from_polygons(reversed);
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_edges(), 4);
+    }
+
+    #[test]
+    fn mirror_images() {
+        // a 2x1 rectangle cut along its diagonal into two triangles that are only congruent by rotation
+        let triangles = vec![
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, 0.0),
+                Vec2::new(2.0, 1.0),
+            ],
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, 1.0),
+                Vec2::new(0.0, 1.0),
+            ],
+        ];
+        let puzzle = TilingPuzzle::from_polygons(triangles);
+        assert_well_defined(&puzzle);
+        assert!(puzzle.can_exchange(PointKey(0), PointKey(1)));
+        assert!(!puzzle.can_exchange_flipped(PointKey(0), PointKey(1)));
+        assert!(!puzzle.can_exchange_flipped(PointKey(0), PointKey(0)));
+
+        // squares can be turned over
+        let puzzle = TilingPuzzle::new(square_tiles());
+        let point = puzzle.arbitrary_point_on_piece(PieceKey(0));
+        assert!(puzzle.can_exchange_flipped(point, puzzle.next_point_on_piece(point)));
+    }
+
+    #[test]
+    #[should_panic(expected = "not clockwise")]
+    fn counter_clockwise() {
+        let mut tiles = square_tiles();
+        tiles[0].corners.reverse();
+        TilingPuzzle::new(tiles);
+    }
+}
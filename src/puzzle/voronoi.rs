@@ -0,0 +1,180 @@
+use super::{area, Tile, TilingPuzzle, Vec2};
+
+/// sides shorter than this are removed, their corners are merged
+const MIN_SIDE: f64 = 1e-9;
+
+impl TilingPuzzle {
+    /// voronoi diagram of a `rows` x `cols` grid of unit cells clipped to the grid,
+    /// where each cell's seed is randomly moved from the cell center by up to `jitter` (at most 0.5) of the cell size.
+    ///
+    /// pieces can be exchanged if they have the same number of neighbors and the same arrangement of border sides
+    pub fn voronoi(rows: usize, cols: usize, jitter: f64, seed: u64) -> Result<Self, String> {
+        if rows * cols < 2 {
+            return Err(format!(
+                "a {rows}x{cols} voronoi diagram has no edges, it needs at least two cells"
+            ));
+        }
+        assert!((0.0..=0.5).contains(&jitter));
+        let mut rng = SplitMix64(seed);
+        let seeds = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                let dx = jitter * (2.0 * rng.next_f64() - 1.0);
+                let dy = jitter * (2.0 * rng.next_f64() - 1.0);
+                Vec2::new(col as f64 + 0.5 + dx, row as f64 + 0.5 + dy)
+            })
+            .collect::<Vec<_>>();
+
+        let (width, height) = (cols as f64, rows as f64);
+        let bounds = vec![
+            (Vec2::new(0.0, 0.0), None),
+            (Vec2::new(width, 0.0), None),
+            (Vec2::new(width, height), None),
+            (Vec2::new(0.0, height), None),
+        ];
+        let cells = seeds
+            .iter()
+            .enumerate()
+            .map(|(a, &seed_a)| {
+                seeds.iter().enumerate().filter(|&(b, _)| b != a).fold(
+                    bounds.clone(),
+                    |cell, (b, &seed_b)| {
+                        // keep the side closer to seed_a
+                        let normal = seed_b - seed_a;
+                        let offset = (seed_b.dot(seed_b) - seed_a.dot(seed_a)) / 2.0;
+                        clip(&cell, normal, offset, b)
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let tiles = cells
+            .iter()
+            .enumerate()
+            .map(|(a, cell)| {
+                for &(_, neighbor) in cell {
+                    if let Some(b) = neighbor {
+                        if !cells[b].iter().any(|&(_, n)| n == Some(a)) {
+                            return Err(format!(
+                                "degenerate voronoi diagram between cells {a} and {b}, try another seed"
+                            ));
+                        }
+                    }
+                }
+                let tile = Tile {
+                    corners: cell.iter().map(|&(corner, _)| corner).collect(),
+                    neighbors: cell.iter().map(|&(_, neighbor)| neighbor).collect(),
+                    side_kinds: vec![0; cell.len()],
+                    mirror_side_kinds: vec![0; cell.len()],
+                };
+                debug_assert!(area(&tile.corners) > 0.0);
+                Ok(tile)
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self::new(tiles))
+    }
+}
+
+/// clip a convex polygon to the half plane `normal . p <= offset`, labeling the new side with `label`.
+/// each corner is paired with the label of the side that starts at it
+fn clip(
+    polygon: &[(Vec2, Option<usize>)],
+    normal: Vec2,
+    offset: f64,
+    label: usize,
+) -> Vec<(Vec2, Option<usize>)> {
+    let inside = |p: Vec2| normal.dot(p) <= offset;
+    let intersect = |p: Vec2, q: Vec2| {
+        let t = (offset - normal.dot(p)) / normal.dot(q - p);
+        p + (q - p) * t
+    };
+    let mut result: Vec<(Vec2, Option<usize>)> = vec![];
+    for (i, &(p, side)) in polygon.iter().enumerate() {
+        let q = polygon[(i + 1) % polygon.len()].0;
+        match (inside(p), inside(q)) {
+            (true, true) => result.push((p, side)),
+            (true, false) => {
+                result.push((p, side));
+                result.push((intersect(p, q), Some(label)));
+            }
+            (false, true) => result.push((intersect(p, q), side)),
+            (false, false) => {}
+        }
+    }
+    // merge corners that are too close together, keeping the label of the later side
+    let mut i = 0;
+    while i < result.len() && result.len() > 1 {
+        let next = (i + 1) % result.len();
+        if (result[next].0 - result[i].0).length() < MIN_SIDE {
+            result.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    result
+}
+
+/// small deterministic random number generator, so results are reproducible from a seed
+struct SplitMix64(u64);
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// uniform in `0.0..1.0`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::puzzle::tests::assert_well_defined;
+    use crate::puzzle::*;
+
+    #[test]
+    fn well_defined() {
+        for seed in 0..10 {
+            let puzzle = TilingPuzzle::voronoi(4, 5, 0.4, seed).unwrap();
+            assert_well_defined(&puzzle);
+            assert_eq!(puzzle.num_pieces(), 20);
+            let total_area = puzzle
+                .tiles()
+                .iter()
+                .map(|tile| area(&tile.corners))
+                .sum::<f64>();
+            assert!((total_area - 20.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn no_jitter_is_a_grid() {
+        let puzzle = TilingPuzzle::voronoi(3, 3, 0.0, 0).unwrap();
+        assert_well_defined(&puzzle);
+        assert_eq!(puzzle.num_edges(), 12);
+        // corners, edges, and center like a square puzzle without an isolated corner
+        let center = puzzle.arbitrary_point_on_piece(PieceKey(4));
+        assert!(puzzle_points(&puzzle)
+            .filter(|&point| puzzle.can_exchange(point, center))
+            .all(|point| puzzle.point_piece(point) == PieceKey(4)));
+    }
+
+    #[test]
+    fn seeded() {
+        let a = TilingPuzzle::voronoi(3, 4, 0.4, 7).unwrap();
+        let b = TilingPuzzle::voronoi(3, 4, 0.4, 7).unwrap();
+        let c = TilingPuzzle::voronoi(3, 4, 0.4, 8).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn too_small() {
+        assert!(TilingPuzzle::voronoi(1, 1, 0.4, 0).is_err());
+        assert!(TilingPuzzle::voronoi(0, 3, 0.4, 0).is_err());
+        assert!(TilingPuzzle::voronoi(1, 2, 0.4, 0).is_ok());
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt::Write;
+
+use crate::puzzle::{centroid, puzzle_pieces, PuzzleGeometry, Vec2};
+use crate::shapes::{cubic_point, Cubic};
+
+/// pixels per unit of puzzle coordinates
+const SCALE: f64 = 40.0;
+const MARGIN: f64 = 10.0;
+
+/// maps puzzle coordinates to svg coordinates with everything in view
+struct SvgFrame {
+    min: Vec2,
+    max: Vec2,
+}
+impl SvgFrame {
+    fn new(points: impl Iterator<Item = Vec2>) -> Self {
+        let (min, max) = points.fold(
+            (Vec2::new(f64::MAX, f64::MAX), Vec2::new(f64::MIN, f64::MIN)),
+            |(min, max), p| {
+                (
+                    Vec2::new(min.x.min(p.x), min.y.min(p.y)),
+                    Vec2::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        );
+        Self { min, max }
+    }
+    fn to_svg(&self, p: Vec2) -> Vec2 {
+        (p - self.min) * SCALE + Vec2::new(MARGIN, MARGIN)
+    }
+    fn header(&self) -> String {
+        let size = self.to_svg(self.max) + Vec2::new(MARGIN, MARGIN);
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}">"#,
+            size.x, size.y
+        )
+        .unwrap();
+        svg
+    }
+}
+
+/// svg drawing of the board with each piece outlined and labeled
+pub fn board_svg(puzzle: &impl PuzzleGeometry) -> String {
+    let pieces = puzzle_pieces(puzzle)
+        .map(|piece| (piece, puzzle.piece_corners(piece)))
+        .collect::<Vec<_>>();
+    let frame = SvgFrame::new(
+        pieces
+            .iter()
+            .flat_map(|(_, corners)| corners.iter().copied()),
+    );
+
+    let mut svg = frame.header();
+    for (piece, corners) in &pieces {
+        let points = corners
+            .iter()
+            .map(|&corner| {
+                let p = frame.to_svg(corner);
+                format!("{:.2},{:.2}", p.x, p.y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            svg,
+            r#"  <polygon points="{points}" fill="none" stroke="black"/>"#
+        )
+        .unwrap();
+        let center = frame.to_svg(centroid(corners));
+        writeln!(
+            svg,
+            r#"  <text x="{:.2}" y="{:.2}" font-size="10" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+            center.x,
+            center.y,
+            puzzle.format_piece(*piece)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// svg with a path for each cut, for a laser cutter
+pub fn cut_svg(paths: &[Vec<Cubic>]) -> String {
+    let frame = SvgFrame::new(paths.iter().flatten().flatten().copied());
+    let mut svg = frame.header();
+    for path in paths {
+        let start = frame.to_svg(path[0][0]);
+        let mut d = format!("M {:.3} {:.3}", start.x, start.y);
+        for cubic in path {
+            d.push_str(" C");
+            for &p in &cubic[1..] {
+                let p = frame.to_svg(p);
+                write!(d, " {:.3} {:.3}", p.x, p.y).unwrap();
+            }
+        }
+        writeln!(
+            svg,
+            r#"  <path d="{d}" fill="none" stroke="red" stroke-width="0.5"/>"#
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// line segments each cubic is approximated by in dxf
+const DXF_SEGMENTS: usize = 8;
+
+/// dxf with the cuts approximated by lines, in units of puzzle coordinates with y pointing up
+pub fn cut_dxf(paths: &[Vec<Cubic>]) -> String {
+    let mut dxf = String::from("0\nSECTION\n2\nENTITIES\n");
+    for cubic in paths.iter().flatten() {
+        for i in 0..DXF_SEGMENTS {
+            let a = cubic_point(cubic, i as f64 / DXF_SEGMENTS as f64);
+            let b = cubic_point(cubic, (i + 1) as f64 / DXF_SEGMENTS as f64);
+            write!(
+                dxf,
+                "0\nLINE\n8\n0\n10\n{:.6}\n20\n{:.6}\n11\n{:.6}\n21\n{:.6}\n",
+                a.x, -a.y, b.x, -b.y
+            )
+            .unwrap();
+        }
+    }
+    dxf.push_str("0\nENDSEC\n0\nEOF\n");
+    dxf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::*;
+
+    #[test]
+    fn square_board() {
+        let svg = board_svg(&SquarePuzzle::new(2, 3));
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<polygon").count(), 6);
+        assert!(svg.contains(">b3</text>"));
+    }
+
+    #[test]
+    fn cuts() {
+        let paths = vec![vec![[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(3.0, 1.0),
+        ]]];
+        let svg = cut_svg(&paths);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains(r#"d="M 10.000 10.000 C 50.000 10.000 90.000 10.000 130.000 50.000""#));
+        let dxf = cut_dxf(&paths);
+        assert_eq!(dxf.matches("LINE").count(), DXF_SEGMENTS);
+        assert!(dxf.ends_with("EOF\n"));
+    }
+
+    #[test]
+    fn tilings() {
+        let svg = board_svg(&TilingPuzzle::rhombille(2, 2));
+        assert_eq!(svg.matches("<polygon").count(), 12);
+        let svg = board_svg(&TilingPuzzle::cairo(2, 2).unwrap());
+        assert_eq!(
+            svg.matches("<polygon").count(),
+            TilingPuzzle::cairo(2, 2).unwrap().num_pieces()
+        );
+    }
+}
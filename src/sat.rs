@@ -1,29 +1,175 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
 
 use itertools::Itertools;
-use varisat::cnf::CnfFormula;
-use varisat::{ExtendFormula, Lit, Solver, Var};
+use varisat::{ExtendFormula, Lit, Solver};
+
+pub use cardinality::*;
+pub use external::*;
+pub use pseudo_boolean::*;
+
+mod cardinality;
+mod external;
+mod pseudo_boolean;
+
+/// a SAT solver that clauses are added to
+pub trait SatBackend {
+    /// allocate a new var
+    fn new_var(&mut self) -> Lit;
+    fn add_clause(&mut self, clause: &[Lit]);
+    /// a solution of all clauses that makes the assumptions true, or the assumptions that prevent one
+    fn solve(&mut self, assumptions: &[Lit]) -> Result<SolveResult, String>;
+}
+
+/// a solution, or the assumptions that can't all be true together
+#[derive(Debug, PartialEq, Eq)]
+pub enum SolveResult {
+    Solution(SatSolution),
+    /// some of the assumptions that have no solution together, empty if there are no solutions at all
+    Failed(Vec<Lit>),
+}
+impl SatBackend for Solver<'static> {
+    fn new_var(&mut self) -> Lit {
+        ExtendFormula::new_var(self).positive()
+    }
+    fn add_clause(&mut self, clause: &[Lit]) {
+        ExtendFormula::add_clause(self, clause);
+    }
+    fn solve(&mut self, assumptions: &[Lit]) -> Result<SolveResult, String> {
+        self.assume(assumptions);
+        let result = Solver::solve(self).map(|satisfiable| match satisfiable {
+            true => SolveResult::Solution(SatSolution::new(&self.model().unwrap())),
+            false => SolveResult::Failed(self.failed_core().unwrap_or_default().to_vec()),
+        });
+        self.assume(&[]);
+        result.map_err(|err| err.to_string())
+    }
+}
+
+/// whether to look for the smallest or the largest total weight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Minimize,
+    Maximize,
+}
 
 pub struct SatProblem {
-    cnf: Solver<'static>,
+    cnf: Box<dyn SatBackend>,
+    count_encoding: CountEncoding,
+    num_vars: usize,
+    /// every clause added, if recording
+    clauses: Option<Vec<Vec<Lit>>>,
+    /// what vars mean, if recording
+    names: BTreeMap<Lit, String>,
+    /// weighted constraints added, if recording
+    linear_constraints: Vec<LinearConstraint>,
+}
+impl Default for SatProblem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl SatProblem {
     pub fn new() -> Self {
-        Self { cnf: Solver::new() }
+        Self::with_backend(Box::new(Solver::new()))
+    }
+    pub fn with_backend(cnf: Box<dyn SatBackend>) -> Self {
+        Self {
+            cnf,
+            count_encoding: CountEncoding::default(),
+            num_vars: 0,
+            clauses: None,
+            names: BTreeMap::new(),
+            linear_constraints: vec![],
+        }
     }
+    /// panics if the backend fails, which the default configuration of varisat never does
     pub fn solve(&mut self) -> Option<SatSolution> {
-        // default configuration of Solver should never fail
-        let satisfiable = self.cnf.solve().unwrap();
-        if satisfiable {
-            Some(SatSolution::new(&self.cnf.model().unwrap()))
-        } else {
-            None
+        self.try_solve().unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// like `solve`, but returns errors of external solvers
+    pub fn try_solve(&mut self) -> Result<Option<SatSolution>, String> {
+        self.solve_assuming(&[])
+    }
+    /// solve with the assumptions only true for this solve, without changing the clauses
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> Result<SolveResult, String> {
+        self.cnf.solve(assumptions)
+    }
+    fn solve_assuming(&mut self, assumptions: &[Lit]) -> Result<Option<SatSolution>, String> {
+        match self.solve_with_assumptions(assumptions)? {
+            SolveResult::Solution(solution) => Ok(Some(solution)),
+            SolveResult::Failed(_) => Ok(None),
         }
     }
+    /// find a solution with the smallest or largest total weight of true vars,
+    /// by solving with a tighter and tighter bound until there are no solutions left.
+    /// the bounds share one BDD of the weighted sum.
+    /// the optimal bound is kept, so any later solutions are optimal too
+    pub fn optimize(
+        &mut self,
+        goal: Goal,
+        weighted_vars: &[(usize, Lit)],
+    ) -> Result<Option<(SatSolution, usize)>, String> {
+        let mut sum = WeightedSum::new(weighted_vars);
+        let Some(mut best) = self.try_solve()? else {
+            return Ok(None);
+        };
+        let mut best_weight = sum.value(&best);
+        loop {
+            let tighter = match goal {
+                Goal::Minimize if best_weight == 0 => break,
+                Goal::Minimize => self.sum_at_most_var(&mut sum, best_weight - 1),
+                Goal::Maximize if best_weight == sum.total() => break,
+                Goal::Maximize => self.sum_at_least_var(&mut sum, best_weight + 1),
+            };
+            let Some(solution) = self.solve_assuming(&[tighter])? else {
+                break;
+            };
+            best_weight = sum.value(&solution);
+            best = solution;
+        }
+        let optimal = match goal {
+            Goal::Minimize => self.sum_at_most_var(&mut sum, best_weight),
+            Goal::Maximize => self.sum_at_least_var(&mut sum, best_weight),
+        };
+        self.or_clause(&[optimal]);
+        Ok(Some((best, best_weight)))
+    }
 
     /// allocate a new var
     pub fn var(&mut self) -> Lit {
-        self.cnf.new_var().positive()
+        self.num_vars += 1;
+        self.cnf.new_var()
+    }
+    /// keep every clause added from now on, so they can be written out with `write_dimacs`
+    pub fn record_clauses(&mut self) {
+        self.clauses.get_or_insert_with(Vec::new);
+    }
+    /// describe what a var means in written out clauses, if it isn't described yet.
+    /// the name is only made when recording
+    pub fn name_var(&mut self, var: Lit, name: impl FnOnce() -> String) {
+        if self.clauses.is_some() {
+            self.names.entry(var.var().positive()).or_insert_with(name);
+        }
+    }
+    /// write the recorded clauses in DIMACS CNF format, with a comment naming each described var,
+    /// and a `c ind` line projecting solutions onto `projection` for model counters
+    pub fn write_dimacs(&self, out: &mut impl Write, projection: &[Lit]) -> io::Result<()> {
+        let clauses = self
+            .clauses
+            .as_ref()
+            .expect("clauses must be recorded to write them");
+        for (var, name) in &self.names {
+            writeln!(out, "c {} {}", var.to_dimacs(), name)?;
+        }
+        writeln!(out, "p cnf {} {}", self.num_vars, clauses.len())?;
+        let projection = projection
+            .iter()
+            .map(|var| var.var().to_dimacs())
+            .sorted()
+            .dedup();
+        writeln!(out, "c ind {} 0", projection.format(" "))?;
+        write_clauses(out, clauses)
     }
     // pub fn printed_var(&mut self, name: &str) -> Lit {
     //     let var = self.var();
@@ -36,6 +182,9 @@ impl SatProblem {
             panic!("trivially not satisfiable")
         }
         self.cnf.add_clause(vars);
+        if let Some(clauses) = &mut self.clauses {
+            clauses.push(vars.to_vec());
+        }
     }
     pub fn nor_clause(&mut self, vars: &[Lit]) {
         let not_vars = vars.iter().map(|v| !*v).collect_vec();
@@ -58,17 +207,20 @@ impl SatProblem {
     }
 
     pub fn exact_count_clause(&mut self, count: usize, vars: &[Lit]) {
-        // short-circuit / optimize a few obvious edge cases
-        if count == 0 {
-            self.nor_clause(vars);
-        } else if count == vars.len() {
-            self.and_clause(vars);
-        } else if count > vars.len() {
-            panic!("trivially not satisfiable")
-        } else {
-            let count_greater_than = self.count_up_to_vars(count + 1, vars);
-            self.and_clause(&[count_greater_than[count - 1], !count_greater_than[count]]);
-        }
+        self.exact_count_clause_with(self.count_encoding, count, vars);
+    }
+
+    pub fn at_most_count_clause(&mut self, count: usize, vars: &[Lit]) {
+        self.at_most_count_clause_with(self.count_encoding, count, vars);
+    }
+
+    pub fn at_least_count_clause(&mut self, count: usize, vars: &[Lit]) {
+        self.at_least_count_clause_with(self.count_encoding, count, vars);
+    }
+
+    /// at least `min` and at most `max` of the vars are true
+    pub fn count_between_clause(&mut self, min: usize, max: usize, vars: &[Lit]) {
+        self.count_between_clause_with(self.count_encoding, min, max, vars);
     }
 
     // tseytin transform
@@ -123,12 +275,17 @@ impl SatProblem {
         if up_to == 0 {
             return prior;
         }
-        for v in vars {
+        for (i, v) in vars.iter().enumerate() {
             let row_len = up_to.min(prior.len() + 1);
             prior = (0..row_len)
-                .into_iter()
                 .map(|c| self.count_var(&prior, *v, c))
                 .collect_vec();
+            for (c, &var) in prior.iter().enumerate() {
+                // the first count is the first var itself
+                if i > 0 {
+                    self.name_var(var, || format!("counter: more than {c} of {} vars", i + 1));
+                }
+            }
         }
         prior
     }
@@ -141,7 +298,7 @@ impl SatProblem {
         debug_assert!(count_greater_than <= prior_greater_than.len());
 
         if count_greater_than == 0 {
-            if let Some(prior) = prior_greater_than.get(0) {
+            if let Some(prior) = prior_greater_than.first() {
                 // prior | var
                 return self.or_var(&[*prior, var]);
             } else {
@@ -184,17 +341,23 @@ impl SatProblem {
     }
 }
 
+/// DIMACS CNF clauses, one per line
+fn write_clauses(out: &mut impl Write, clauses: &[impl AsRef<[Lit]>]) -> io::Result<()> {
+    for clause in clauses {
+        let clause = clause.as_ref();
+        let lits = clause.iter().map(|lit| lit.to_dimacs());
+        writeln!(out, "{} 0", lits.format(" "))?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct SatSolution {
     true_vars: HashSet<Lit>,
 }
 impl SatSolution {
     pub fn new(vars: &[Lit]) -> Self {
-        let true_vars = vars
-            .into_iter()
-            .copied()
-            .filter(|v| v.is_positive())
-            .collect();
+        let true_vars = vars.iter().copied().filter(|v| v.is_positive()).collect();
         Self { true_vars }
     }
     pub fn get(&self, var: Lit) -> bool {
@@ -208,6 +371,10 @@ impl SatSolution {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
+
+    use varisat::Var;
+
     use super::*;
 
     fn assert_all_solutions(mut p: SatProblem, vars: Vec<Lit>, mut solutions: Vec<SatSolution>) {
@@ -265,6 +432,19 @@ mod tests {
             panic!("unexpected solution {solution:?} given vars {vars:?}");
         }
     }
+    /// every assignment of the vars in some solution
+    pub(super) fn all_solutions(mut p: SatProblem, vars: &[Lit]) -> BTreeSet<Vec<bool>> {
+        let mut solutions = BTreeSet::new();
+        while let Some(solution) = p.solve() {
+            let values = vars.iter().map(|&var| solution.get(var)).collect_vec();
+            assert!(solutions.insert(values), "repeated solution");
+            if vars.is_empty() {
+                break;
+            }
+            exclude_solution(&mut p, vars, &solution);
+        }
+        solutions
+    }
     fn exclude_solution(p: &mut SatProblem, vars: &[Lit], solution: &SatSolution) {
         let clause = vars
             .iter()
@@ -282,13 +462,18 @@ mod tests {
         SatSolution::new(&true_vars)
     }
 
+    #[allow(dead_code)] // for debugging tests
     fn print_all_solutions(mut p: SatProblem) {
         while print_and_exclude_next_solution(&mut p) {}
     }
     fn print_and_exclude_next_solution(p: &mut SatProblem) -> bool {
-        let satisfiable = p.cnf.solve().unwrap();
-        if satisfiable {
-            let vars = p.cnf.model().unwrap();
+        let solution = p.solve();
+        let satisfiable = solution.is_some();
+        if let Some(solution) = solution {
+            let vars = (0..p.num_vars)
+                .map(|index| Var::from_index(index).positive())
+                .map(|var| if solution.get(var) { var } else { !var })
+                .collect_vec();
             println!(
                 "solution [{}]",
                 vars.iter().filter(|v| v.is_positive()).format(",")
@@ -488,12 +673,13 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "trivially not satisfiable")]
     fn exact_count_clause_3_2() {
         let mut p = SatProblem::new();
         let a = p.var();
         let b = p.var();
         p.exact_count_clause(3, &[a, b]);
+
+        assert_eq!(p.solve(), None);
     }
 
     #[test]
@@ -556,6 +742,173 @@ mod tests {
         assert_all_solutions_ignore_hidden(p, vec![a, b, c], vec![SatSolution::new(&[a, b, c])]);
     }
 
+    #[test]
+    fn at_most_count_clause_0_2() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        p.at_most_count_clause(0, &[a, b]);
+
+        assert_all_solutions_ignore_hidden(p, vec![a, b], vec![SatSolution::new(&[])]);
+    }
+
+    #[test]
+    fn at_most_count_clause_1_3() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.at_most_count_clause(1, &[a, b, c]);
+
+        assert_all_solutions_ignore_hidden(
+            p,
+            vec![a, b, c],
+            vec![
+                SatSolution::new(&[]),
+                SatSolution::new(&[a]),
+                SatSolution::new(&[b]),
+                SatSolution::new(&[c]),
+            ],
+        );
+    }
+
+    #[test]
+    fn at_most_count_clause_2_3() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.at_most_count_clause(2, &[a, b, c]);
+
+        assert_all_solutions_ignore_hidden(
+            p,
+            vec![a, b, c],
+            vec![
+                SatSolution::new(&[]),
+                SatSolution::new(&[a]),
+                SatSolution::new(&[b]),
+                SatSolution::new(&[c]),
+                SatSolution::new(&[a, b]),
+                SatSolution::new(&[a, c]),
+                SatSolution::new(&[b, c]),
+            ],
+        );
+    }
+
+    #[test]
+    fn at_most_count_clause_3_2() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        p.at_most_count_clause(3, &[a, b]);
+
+        assert_all_solutions_ignore_hidden(
+            p,
+            vec![a, b],
+            vec![
+                SatSolution::new(&[]),
+                SatSolution::new(&[a]),
+                SatSolution::new(&[b]),
+                SatSolution::new(&[a, b]),
+            ],
+        );
+    }
+
+    #[test]
+    fn at_least_count_clause_2_3() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.at_least_count_clause(2, &[a, b, c]);
+
+        assert_all_solutions_ignore_hidden(
+            p,
+            vec![a, b, c],
+            vec![
+                SatSolution::new(&[a, b]),
+                SatSolution::new(&[a, c]),
+                SatSolution::new(&[b, c]),
+                SatSolution::new(&[a, b, c]),
+            ],
+        );
+    }
+
+    #[test]
+    fn solve_with_assumptions() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.or_clause(&[a, b]);
+
+        match p.solve_with_assumptions(&[!a, c]).unwrap() {
+            SolveResult::Solution(solution) => {
+                assert!(solution.get(b));
+                assert!(solution.get(c));
+            }
+            SolveResult::Failed(failed) => panic!("unexpected failed assumptions {failed:?}"),
+        }
+        // `c` isn't needed for the conflict, so it isn't in the failed assumptions
+        match p.solve_with_assumptions(&[c, !a, !b]).unwrap() {
+            SolveResult::Solution(solution) => panic!("unexpected solution {solution:?}"),
+            SolveResult::Failed(failed) => {
+                assert_eq!(failed.iter().copied().sorted().collect_vec(), vec![!a, !b]);
+            }
+        }
+        // assumptions don't stay
+        assert!(p.solve().is_some());
+
+        p.nor_clause(&[a, b]);
+        assert_eq!(
+            p.solve_with_assumptions(&[c]),
+            Ok(SolveResult::Failed(vec![]))
+        );
+    }
+
+    #[test]
+    fn optimize_minimize() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.or_clause(&[a, b]);
+        p.or_clause(&[b, c]);
+        let (solution, weight) = p
+            .optimize(Goal::Minimize, &[(1, a), (3, b), (1, c)])
+            .unwrap()
+            .unwrap();
+        assert_eq!(weight, 2);
+        assert!(solution.get(a) && !solution.get(b) && solution.get(c));
+
+        // the optimal bound is kept
+        assert_all_solutions_ignore_hidden(p, vec![a, b, c], vec![SatSolution::new(&[a, c])]);
+    }
+
+    #[test]
+    fn optimize_maximize() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.exact_count_clause(2, &[a, b, c]);
+        let (_, weight) = p
+            .optimize(Goal::Maximize, &[(2, a), (1, b), (2, c)])
+            .unwrap()
+            .unwrap();
+        assert_eq!(weight, 4);
+
+        assert_all_solutions_ignore_hidden(p, vec![a, b, c], vec![SatSolution::new(&[a, c])]);
+    }
+
+    #[test]
+    fn optimize_unsatisfiable() {
+        let mut p = SatProblem::new();
+        let a = p.var();
+        p.and_clause(&[a, !a]);
+        assert_eq!(p.optimize(Goal::Maximize, &[(1, a)]), Ok(None));
+    }
+
     #[test]
     fn and_var_0() {
         let mut p = SatProblem::new();
@@ -985,4 +1338,36 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn write_dimacs() {
+        let mut p = SatProblem::new();
+        p.record_clauses();
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        p.name_var(a, || "a".to_owned());
+        p.name_var(!b, || "b".to_owned());
+        p.name_var(b, || "not used".to_owned());
+        p.or_clause(&[a, !b]);
+        p.at_most_count_clause(1, &[a, b, c]);
+
+        let mut out = vec![];
+        p.write_dimacs(&mut out, &[c, a, !a]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines = text.lines().collect_vec();
+        assert_eq!(lines[..2], ["c 1 a", "c 2 b"]);
+        assert!(lines.iter().any(|line| line.starts_with("c 4 counter: ")));
+        let header = lines
+            .iter()
+            .position(|line| line.starts_with("p cnf"))
+            .unwrap();
+        let num_clauses = lines.len() - header - 2;
+        assert_eq!(
+            lines[header],
+            format!("p cnf {} {}", p.num_vars, num_clauses)
+        );
+        assert_eq!(lines[header + 1], "c ind 1 3 0");
+        assert_eq!(lines[header + 2], "1 -2 0");
+    }
 }
@@ -0,0 +1,479 @@
+use itertools::Itertools;
+use varisat::Lit;
+
+use super::SatProblem;
+
+/// how to encode how many of some vars are true
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CountEncoding {
+    /// a counter for each prefix of the vars, up to the count
+    #[default]
+    Sequential,
+    /// a clause for every pair of vars
+    Pairwise,
+    /// groups of three vars with a commander var each, and at most one commander
+    Commander,
+    /// vars on a grid, with at most one row and at most one column
+    Product,
+    /// a ladder of vars for whether any var so far is true
+    Ladder,
+    /// a tree of counts of halves of the vars
+    Totalizer,
+    /// an odd-even merge sorting network that puts the true vars first
+    SortingNetwork,
+}
+impl CountEncoding {
+    pub const ALL: [Self; 7] = [
+        Self::Sequential,
+        Self::Pairwise,
+        Self::Commander,
+        Self::Product,
+        Self::Ladder,
+        Self::Totalizer,
+        Self::SortingNetwork,
+    ];
+
+    /// whether it only encodes at most one true var, other counts fall back to `Sequential`
+    pub fn at_most_one_only(self) -> bool {
+        matches!(
+            self,
+            Self::Pairwise | Self::Commander | Self::Product | Self::Ladder
+        )
+    }
+}
+
+impl SatProblem {
+    /// encoding used by `exact_count_clause`, `at_most_count_clause` and `at_least_count_clause`
+    pub fn set_count_encoding(&mut self, encoding: CountEncoding) {
+        self.count_encoding = encoding;
+    }
+
+    pub fn exact_count_clause_with(&mut self, encoding: CountEncoding, count: usize, vars: &[Lit]) {
+        // short-circuit / optimize a few obvious edge cases
+        if count == 0 {
+            self.nor_clause(vars);
+        } else if count == vars.len() {
+            self.and_clause(vars);
+        } else if count > vars.len() {
+            self.unsatisfiable_clause()
+        } else if count == 1 && encoding.at_most_one_only() {
+            self.at_most_one_clause(encoding, vars);
+            self.or_clause(vars);
+        } else {
+            let count_greater_than = self.count_greater_than_vars(encoding, count + 1, vars);
+            self.and_clause(&[count_greater_than[count - 1], !count_greater_than[count]]);
+        }
+    }
+
+    pub fn at_most_count_clause_with(
+        &mut self,
+        encoding: CountEncoding,
+        count: usize,
+        vars: &[Lit],
+    ) {
+        if count == 0 {
+            self.nor_clause(vars);
+        } else if count == 1 && encoding.at_most_one_only() {
+            self.at_most_one_clause(encoding, vars);
+        } else if count < vars.len() {
+            let count_greater_than = self.count_greater_than_vars(encoding, count + 1, vars);
+            self.not_clause(count_greater_than[count]);
+        }
+    }
+
+    pub fn at_least_count_clause_with(
+        &mut self,
+        encoding: CountEncoding,
+        count: usize,
+        vars: &[Lit],
+    ) {
+        if count > vars.len() {
+            self.unsatisfiable_clause()
+        } else if count == 1 && encoding.at_most_one_only() {
+            self.or_clause(vars);
+        } else if count > 0 {
+            let count_greater_than = self.count_greater_than_vars(encoding, count, vars);
+            self.or_clause(&[count_greater_than[count - 1]]);
+        }
+    }
+
+    pub fn count_between_clause_with(
+        &mut self,
+        encoding: CountEncoding,
+        min: usize,
+        max: usize,
+        vars: &[Lit],
+    ) {
+        if min > max || min > vars.len() {
+            self.unsatisfiable_clause()
+        } else if min == max {
+            self.exact_count_clause_with(encoding, min, vars);
+        } else if min == 0 {
+            self.at_most_count_clause_with(encoding, max, vars);
+        } else if max >= vars.len() {
+            self.at_least_count_clause_with(encoding, min, vars);
+        } else {
+            let count_greater_than = self.count_greater_than_vars(encoding, max + 1, vars);
+            self.and_clause(&[count_greater_than[min - 1], !count_greater_than[max]]);
+        }
+    }
+
+    // reified counts, true exactly when the count is right, so they can be used in other clauses.
+    // encodings that only encode at most one use `Sequential` instead
+    pub fn exact_count_var(&mut self, count: usize, vars: &[Lit]) -> Lit {
+        self.count_range_var(count, Some(count), vars)
+    }
+    pub fn at_most_count_var(&mut self, count: usize, vars: &[Lit]) -> Lit {
+        self.count_range_var(0, Some(count), vars)
+    }
+    pub fn at_least_count_var(&mut self, count: usize, vars: &[Lit]) -> Lit {
+        self.count_range_var(count, None, vars)
+    }
+    pub fn count_between_var(&mut self, min: usize, max: usize, vars: &[Lit]) -> Lit {
+        self.count_range_var(min, Some(max), vars)
+    }
+    /// whether at least `min` and at most `max` of the vars are true, `None` for no upper limit
+    fn count_range_var(&mut self, min: usize, max: Option<usize>, vars: &[Lit]) -> Lit {
+        let max = max.filter(|&max| max < vars.len());
+        if min > vars.len() || max.is_some_and(|max| max < min) {
+            return self.constant_var(false);
+        }
+        let up_to = max.map_or(min, |max| max + 1);
+        let count_greater_than = self.count_greater_than_vars(self.count_encoding, up_to, vars);
+        let mut conditions = vec![];
+        if min > 0 {
+            conditions.push(count_greater_than[min - 1]);
+        }
+        if let Some(max) = max {
+            conditions.push(!count_greater_than[max]);
+        }
+        match conditions[..] {
+            [] => self.constant_var(true),
+            [condition] => condition,
+            _ => self.and_var(&conditions),
+        }
+    }
+    pub(super) fn constant_var(&mut self, value: bool) -> Lit {
+        let var = self.var();
+        self.or_clause(&[if value { var } else { !var }]);
+        var
+    }
+    /// a constraint that can't be met, so the problem has no solutions
+    pub(super) fn unsatisfiable_clause(&mut self) {
+        let never = self.constant_var(false);
+        self.or_clause(&[never]);
+    }
+
+    /// vars for whether more than 0, 1, ... of the vars are true, up to `up_to` or the number of vars
+    fn count_greater_than_vars(
+        &mut self,
+        encoding: CountEncoding,
+        up_to: usize,
+        vars: &[Lit],
+    ) -> Vec<Lit> {
+        if up_to == 0 {
+            return vec![];
+        }
+        match encoding {
+            CountEncoding::Totalizer if !vars.is_empty() => self.totalizer_vars(up_to, vars),
+            CountEncoding::SortingNetwork => {
+                let mut sorted = self.sorted_vars(vars);
+                sorted.truncate(up_to);
+                sorted
+            }
+            _ => self.count_up_to_vars(up_to, vars),
+        }
+    }
+
+    fn at_most_one_clause(&mut self, encoding: CountEncoding, vars: &[Lit]) {
+        // a few pairs are cheaper than any extra vars
+        if vars.len() <= 4 || encoding == CountEncoding::Pairwise {
+            for (&a, &b) in vars.iter().tuple_combinations() {
+                self.nand_clause(&[a, b]);
+            }
+            return;
+        }
+        match encoding {
+            CountEncoding::Commander => {
+                let commanders = vars
+                    .chunks(3)
+                    .map(|group| {
+                        self.at_most_one_clause(encoding, group);
+                        self.or_var(group)
+                    })
+                    .collect_vec();
+                self.at_most_one_clause(encoding, &commanders);
+            }
+            CountEncoding::Product => {
+                let cols = (vars.len() as f64).sqrt().ceil() as usize;
+                let rows = vars.chunks(cols).map(|row| self.or_var(row)).collect_vec();
+                let cols = (0..cols)
+                    .map(|col| {
+                        let col = vars.iter().copied().skip(col).step_by(cols).collect_vec();
+                        self.or_var(&col)
+                    })
+                    .collect_vec();
+                self.at_most_one_clause(encoding, &rows);
+                self.at_most_one_clause(encoding, &cols);
+            }
+            CountEncoding::Ladder => {
+                // any_so_far == or(vars[..=i]), built one rung at a time
+                let mut any_so_far = vars[0];
+                for &var in &vars[1..vars.len() - 1] {
+                    self.nand_clause(&[any_so_far, var]);
+                    any_so_far = self.or_var(&[any_so_far, var]);
+                }
+                self.nand_clause(&[any_so_far, vars[vars.len() - 1]]);
+            }
+            _ => unreachable!("{encoding:?} encodes any count"),
+        }
+    }
+
+    /// a totalizer, which counts each half of the vars and adds them up
+    fn totalizer_vars(&mut self, up_to: usize, vars: &[Lit]) -> Vec<Lit> {
+        if vars.len() == 1 {
+            return vars.to_vec();
+        }
+        let (left, right) = vars.split_at(vars.len() / 2);
+        let left = self.totalizer_vars(up_to, left);
+        let right = self.totalizer_vars(up_to, right);
+        let len = up_to.min(left.len() + right.len());
+        let sum = (0..len).map(|_| self.var()).collect_vec();
+        for (&var, c) in sum.iter().zip(0..) {
+            self.name_var(var, || {
+                format!("totalizer: more than {c} of {} vars", vars.len())
+            });
+        }
+        for i in 0..=left.len() {
+            for j in 0..=right.len() {
+                // left > i - 1 & right > j - 1 => sum > i + j - 1
+                if i + j > 0 {
+                    let mut clause = vec![sum[(i + j).min(len) - 1]];
+                    clause.extend(i.checked_sub(1).map(|i| !left[i]));
+                    clause.extend(j.checked_sub(1).map(|j| !right[j]));
+                    self.or_clause(&clause);
+                }
+                // !(left > i) & !(right > j) => !(sum > i + j)
+                if i + j < len {
+                    let mut clause = vec![!sum[i + j]];
+                    clause.extend(left.get(i).copied());
+                    clause.extend(right.get(j).copied());
+                    self.or_clause(&clause);
+                }
+            }
+        }
+        sum
+    }
+
+    /// the vars sorted with true vars first, so the nth is whether more than n are true
+    fn sorted_vars(&mut self, vars: &[Lit]) -> Vec<Lit> {
+        if vars.len() <= 1 {
+            return vars.to_vec();
+        }
+        let (left, right) = vars.split_at(vars.len() / 2);
+        let left = self.sorted_vars(left);
+        let right = self.sorted_vars(right);
+        self.merge_sorted_vars(&left, &right)
+    }
+    /// batcher's odd-even merge, which works for any lengths
+    fn merge_sorted_vars(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        match (a, b) {
+            ([], _) => return b.to_vec(),
+            (_, []) => return a.to_vec(),
+            (&[a], &[b]) => return self.compare_vars(a, b).to_vec(),
+            _ => {}
+        }
+        let evens = |vars: &[Lit]| vars.iter().copied().step_by(2).collect_vec();
+        let odds = |vars: &[Lit]| vars.iter().copied().skip(1).step_by(2).collect_vec();
+        let evens = self.merge_sorted_vars(&evens(a), &evens(b));
+        let odds = self.merge_sorted_vars(&odds(a), &odds(b));
+        let mut merged = vec![evens[0]];
+        let mut evens = evens.into_iter().skip(1);
+        for odd in odds {
+            match evens.next() {
+                Some(even) => merged.extend(self.compare_vars(odd, even)),
+                None => merged.push(odd),
+            }
+        }
+        merged.extend(evens);
+        merged
+    }
+    /// the larger and smaller of two vars
+    fn compare_vars(&mut self, a: Lit, b: Lit) -> [Lit; 2] {
+        let larger = self.or_var(&[a, b]);
+        let smaller = self.and_var(&[a, b]);
+        self.name_var(larger, || "sorting network: larger".to_owned());
+        self.name_var(smaller, || "sorting network: smaller".to_owned());
+        [larger, smaller]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::super::tests::all_solutions;
+    use super::*;
+
+    type AddClause = fn(&mut SatProblem, CountEncoding, usize, usize, &[Lit]);
+    type CountVar = fn(&mut SatProblem, usize, usize, &[Lit]) -> Lit;
+    /// whether a number of true vars is allowed by a count, or a min and max
+    type Allows = fn(usize, usize, usize) -> bool;
+
+    /// all assignments of `len` vars with an allowed number of true vars
+    fn allowed_assignments(len: usize, allows: impl Fn(usize) -> bool) -> BTreeSet<Vec<bool>> {
+        (0..1 << len)
+            .map(|bits: usize| (0..len).map(|i| bits >> i & 1 == 1).collect_vec())
+            .filter(|values| allows(values.iter().filter(|&&value| value).count()))
+            .collect()
+    }
+
+    /// compare every count clause on up to `max_len` vars with the assignments that have the right count
+    fn assert_count_clauses(encoding: CountEncoding, max_len: usize) {
+        let clauses: [(&str, AddClause, Allows); 4] = [
+            (
+                "exact",
+                |p, encoding, count, _, vars| p.exact_count_clause_with(encoding, count, vars),
+                |n, count, _| n == count,
+            ),
+            (
+                "at most",
+                |p, encoding, count, _, vars| p.at_most_count_clause_with(encoding, count, vars),
+                |n, count, _| n <= count,
+            ),
+            (
+                "at least",
+                |p, encoding, count, _, vars| p.at_least_count_clause_with(encoding, count, vars),
+                |n, count, _| n >= count,
+            ),
+            (
+                "between",
+                SatProblem::count_between_clause_with,
+                |n, min, max| (min..=max).contains(&n),
+            ),
+        ];
+        let count_vars: [(&str, CountVar, Allows); 4] = [
+            (
+                "exact",
+                |p, count, _, vars| p.exact_count_var(count, vars),
+                |n, count, _| n == count,
+            ),
+            (
+                "at most",
+                |p, count, _, vars| p.at_most_count_var(count, vars),
+                |n, count, _| n <= count,
+            ),
+            (
+                "at least",
+                |p, count, _, vars| p.at_least_count_var(count, vars),
+                |n, count, _| n >= count,
+            ),
+            ("between", SatProblem::count_between_var, |n, min, max| {
+                (min..=max).contains(&n)
+            }),
+        ];
+        for len in 0..=max_len {
+            // including counts that can't be met, which have no solutions
+            for (count, max) in (0..=len + 1).cartesian_product(0..=len + 1) {
+                for (name, add_clause, allows) in clauses {
+                    // only `between` uses the max, so the others are only checked once
+                    let max = match name {
+                        "between" => max,
+                        _ if max > 0 => continue,
+                        _ => count,
+                    };
+                    let mut p = SatProblem::new();
+                    p.set_count_encoding(encoding);
+                    let vars = (0..len).map(|_| p.var()).collect_vec();
+                    add_clause(&mut p, encoding, count, max, &vars);
+                    assert_eq!(
+                        all_solutions(p, &vars),
+                        allowed_assignments(len, |n| allows(n, count, max)),
+                        "{name} {count} {max} of {len} with {encoding:?}"
+                    );
+                }
+                // also counts that can't be met, which make the var false
+                for (name, count_var, allows) in count_vars {
+                    let count = if name == "between" { count } else { max };
+                    let mut p = SatProblem::new();
+                    p.set_count_encoding(encoding);
+                    let mut vars = (0..len).map(|_| p.var()).collect_vec();
+                    let var = count_var(&mut p, count, max, &vars);
+                    vars.push(var);
+                    let expected = allowed_assignments(len, |_| true)
+                        .into_iter()
+                        .map(|mut values| {
+                            let n = values.iter().filter(|&&value| value).count();
+                            values.push(allows(n, count, max));
+                            values
+                        })
+                        .collect();
+                    assert_eq!(
+                        all_solutions(p, &vars),
+                        expected,
+                        "{name} var {count} {max} of {len} with {encoding:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// compare at most one and exactly one on up to `max_len` vars, past the sizes where pairs are used instead
+    fn assert_at_most_one_clauses(encoding: CountEncoding, max_len: usize) {
+        for len in 0..=max_len {
+            for exact in [false, true] {
+                let mut p = SatProblem::new();
+                let vars = (0..len).map(|_| p.var()).collect_vec();
+                if exact {
+                    p.exact_count_clause_with(encoding, 1, &vars);
+                } else {
+                    p.at_most_count_clause_with(encoding, 1, &vars);
+                }
+                assert_eq!(
+                    all_solutions(p, &vars),
+                    allowed_assignments(len, |n| n == 1 || (n == 0 && !exact)),
+                    "exact {exact} of {len} with {encoding:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sequential() {
+        assert_count_clauses(CountEncoding::Sequential, 5);
+    }
+
+    #[test]
+    fn pairwise() {
+        assert_count_clauses(CountEncoding::Pairwise, 5);
+        assert_at_most_one_clauses(CountEncoding::Pairwise, 14);
+    }
+
+    #[test]
+    fn commander() {
+        assert_count_clauses(CountEncoding::Commander, 6);
+        assert_at_most_one_clauses(CountEncoding::Commander, 14);
+    }
+
+    #[test]
+    fn product() {
+        assert_count_clauses(CountEncoding::Product, 6);
+        assert_at_most_one_clauses(CountEncoding::Product, 14);
+    }
+
+    #[test]
+    fn ladder() {
+        assert_count_clauses(CountEncoding::Ladder, 5);
+        assert_at_most_one_clauses(CountEncoding::Ladder, 14);
+    }
+
+    #[test]
+    fn totalizer() {
+        assert_count_clauses(CountEncoding::Totalizer, 5);
+    }
+
+    #[test]
+    fn sorting_network() {
+        assert_count_clauses(CountEncoding::SortingNetwork, 5);
+    }
+}
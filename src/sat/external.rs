@@ -0,0 +1,205 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use varisat::{Lit, Var};
+
+use super::{write_clauses, SatBackend, SatSolution, SolveResult};
+
+/// a solver binary run on a DIMACS CNF file for every solve, like `kissat` or `cadical`.
+/// it must print `s SATISFIABLE` or `s UNSATISFIABLE`, and the model on `v` lines.
+/// it can't tell which assumptions failed, so they are dropped one at a time and solved again,
+/// leaving a set of them that still conflicts but doesn't without any one of them
+pub struct ExternalSolver {
+    /// program followed by its arguments, the file is passed after them
+    command: Vec<String>,
+    num_vars: usize,
+    clauses: Vec<Vec<Lit>>,
+    path: PathBuf,
+}
+impl ExternalSolver {
+    /// `command` is split on whitespace into the program and its arguments
+    pub fn new(command: &str) -> Result<Self, String> {
+        let command = command
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        if command.is_empty() {
+            return Err("empty solver command".to_owned());
+        }
+        // each solver gets its own file, in case several run at once
+        static SOLVERS: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "jigsaw-doubler-{}-{}.cnf",
+            std::process::id(),
+            SOLVERS.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(name);
+        Ok(Self {
+            command,
+            num_vars: 0,
+            clauses: vec![],
+            path,
+        })
+    }
+    /// run the solver once, returning the model or `None` if unsatisfiable
+    fn run(&mut self, assumptions: &[Lit]) -> Result<Option<Vec<Lit>>, String> {
+        // assumptions are only needed for one solve, so they are added as unit clauses to this file
+        let units = assumptions.iter().map(std::slice::from_ref);
+        let clauses = self
+            .clauses
+            .iter()
+            .map(Vec::as_slice)
+            .chain(units)
+            .collect::<Vec<_>>();
+        let mut contents = vec![];
+        writeln!(contents, "p cnf {} {}", self.num_vars, clauses.len())
+            .and_then(|()| write_clauses(&mut contents, &clauses))
+            .and_then(|()| fs::write(&self.path, contents))
+            .map_err(|err| format!("could not write {:?}: {err}", self.path))?;
+
+        let output = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .arg(&self.path)
+            .output()
+            .map_err(|err| format!("could not run solver {:?}: {err}", self.command[0]))?;
+        let _ = fs::remove_file(&self.path);
+        // solvers exit with 10 or 20 for their answer, so the status isn't checked
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_output(&stdout).ok_or_else(|| {
+            format!(
+                "solver {:?} gave no answer: {}",
+                self.command[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        })
+    }
+}
+impl SatBackend for ExternalSolver {
+    fn new_var(&mut self) -> Lit {
+        self.num_vars += 1;
+        Var::from_dimacs(self.num_vars as isize).positive()
+    }
+    fn add_clause(&mut self, clause: &[Lit]) {
+        self.clauses.push(clause.to_vec());
+    }
+    fn solve(&mut self, assumptions: &[Lit]) -> Result<SolveResult, String> {
+        if let Some(model) = self.run(assumptions)? {
+            return Ok(SolveResult::Solution(SatSolution::new(&model)));
+        }
+        let mut failed = assumptions.to_vec();
+        let mut i = 0;
+        while i < failed.len() {
+            let mut fewer = failed.clone();
+            fewer.remove(i);
+            match self.run(&fewer)? {
+                Some(_) => i += 1,
+                None => failed = fewer,
+            }
+        }
+        Ok(SolveResult::Failed(failed))
+    }
+}
+
+/// the model of `s` and `v` lines, `Some(None)` if unsatisfiable, `None` if there is no answer
+fn parse_output(output: &str) -> Option<Option<Vec<Lit>>> {
+    let mut satisfiable = None;
+    let mut model = vec![];
+    for line in output.lines() {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["s", "SATISFIABLE"] => satisfiable = Some(true),
+            ["s", "UNSATISFIABLE"] => satisfiable = Some(false),
+            ["v", ref lits @ ..] => {
+                let lits = lits.iter().map(|lit| lit.parse::<isize>().ok());
+                for lit in lits {
+                    match lit? {
+                        0 => {}
+                        lit => model.push(Lit::from_dimacs(lit)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    match satisfiable? {
+        true => Some(Some(model)),
+        false => Some(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sat::SatProblem;
+
+    /// a solver that ignores the file and answers with a fixed output, saving the file next to itself
+    fn stub_solver(name: &str, output: &str) -> String {
+        let path = std::env::temp_dir().join(format!("jigsaw-doubler-stub-{name}.sh"));
+        let script = format!("#!/bin/sh\ncp \"$1\" \"$0.cnf\"\nprintf '{output}'\nexit 10\n");
+        fs::write(&path, script).unwrap();
+        format!("sh {}", path.to_str().unwrap())
+    }
+
+    #[test]
+    fn parse_solver_output() {
+        let model = parse_output("c comment\ns SATISFIABLE\nv 1 -2\nv 3 0\n");
+        assert_eq!(
+            model,
+            Some(Some(vec![
+                Lit::from_dimacs(1),
+                Lit::from_dimacs(-2),
+                Lit::from_dimacs(3)
+            ]))
+        );
+        assert_eq!(parse_output("s UNSATISFIABLE\n"), Some(None));
+        assert_eq!(parse_output("c nothing\n"), None);
+        assert_eq!(parse_output("s SATISFIABLE\nv 1 x 0\n"), None);
+    }
+
+    #[test]
+    fn stub_solvers() {
+        let command = stub_solver("sat", "s SATISFIABLE\\nv -1 2 0\\n");
+        let mut p = SatProblem::with_backend(Box::new(ExternalSolver::new(&command).unwrap()));
+        let a = p.var();
+        let b = p.var();
+        p.or_clause(&[a, b]);
+        let solution = p.solve().unwrap();
+        assert!(!solution.get(a));
+        assert!(solution.get(b));
+        let written = command.split_whitespace().last().unwrap().to_owned() + ".cnf";
+        assert_eq!(fs::read_to_string(written).unwrap(), "p cnf 2 1\n1 2 0\n");
+
+        let command = stub_solver("unsat", "s UNSATISFIABLE\\n");
+        let mut p = SatProblem::with_backend(Box::new(ExternalSolver::new(&command).unwrap()));
+        let a = p.var();
+        p.or_clause(&[a]);
+        assert_eq!(p.solve(), None);
+        // unsatisfiable without any assumptions
+        assert_eq!(
+            p.solve_with_assumptions(&[a]),
+            Ok(SolveResult::Failed(vec![]))
+        );
+
+        // only unsatisfiable when both 1 and 2 are assumed
+        let path = std::env::temp_dir().join("jigsaw-doubler-stub-core.sh");
+        let script = "#!/bin/sh\n\
+            if grep -qx '1 0' \"$1\" && grep -qx '2 0' \"$1\"; then echo 's UNSATISFIABLE'; \
+            else echo 's SATISFIABLE'; echo 'v 1 2 3 0'; fi\n";
+        fs::write(&path, script).unwrap();
+        let command = format!("sh {}", path.to_str().unwrap());
+        let mut p = SatProblem::with_backend(Box::new(ExternalSolver::new(&command).unwrap()));
+        let a = p.var();
+        let b = p.var();
+        let c = p.var();
+        assert_eq!(
+            p.solve_with_assumptions(&[c, a, b]),
+            Ok(SolveResult::Failed(vec![a, b]))
+        );
+
+        let mut solver = ExternalSolver::new("/nonexistent/solver").unwrap();
+        assert!(solver.solve(&[]).is_err());
+        assert!(ExternalSolver::new(" ").is_err());
+    }
+}
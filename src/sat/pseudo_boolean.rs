@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use itertools::Itertools;
+use varisat::Lit;
+
+use super::{Goal, SatProblem, SatSolution};
+
+/// a sum of vars times their weights, with the BDD nodes made so far for bounds on it
+pub struct WeightedSum {
+    /// heaviest first, without zero weights
+    terms: Vec<(usize, Lit)>,
+    /// total weight of the terms from each index on
+    remaining: Vec<usize>,
+    /// whether the terms from an index on add up to at most a bound
+    nodes: HashMap<(usize, usize), Lit>,
+}
+impl WeightedSum {
+    pub fn new(weighted_vars: &[(usize, Lit)]) -> Self {
+        let terms = weighted_vars
+            .iter()
+            .copied()
+            .filter(|&(weight, _)| weight > 0)
+            .sorted_by_key(|&(weight, _)| std::cmp::Reverse(weight))
+            .collect_vec();
+        let mut remaining = vec![0; terms.len() + 1];
+        for (i, &(weight, _)) in terms.iter().enumerate().rev() {
+            remaining[i] = remaining[i + 1] + weight;
+        }
+        Self {
+            terms,
+            remaining,
+            nodes: HashMap::new(),
+        }
+    }
+    /// the largest the sum can be
+    pub fn total(&self) -> usize {
+        self.remaining[0]
+    }
+    pub fn value(&self, solution: &SatSolution) -> usize {
+        self.terms
+            .iter()
+            .filter(|&&(_, var)| solution.get(var))
+            .map(|&(weight, _)| weight)
+            .sum()
+    }
+}
+
+/// a BDD node, which is a var unless the bound is always or never met
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Constant(bool),
+    Var(Lit),
+}
+
+/// `sum >= bound` with signed weights, as written to OPB files
+pub(super) struct LinearConstraint {
+    terms: Vec<(i64, Lit)>,
+    bound: i64,
+}
+
+impl SatProblem {
+    /// the weighted sum of the vars is at most `max`
+    pub fn weighted_at_most_clause(&mut self, max: usize, weighted_vars: &[(usize, Lit)]) {
+        self.record_linear(weighted_vars, -1, -(max as i64));
+        let var = self.sum_at_most_var(&mut WeightedSum::new(weighted_vars), max);
+        self.or_clause(&[var]);
+    }
+    /// the weighted sum of the vars is at least `min`
+    pub fn weighted_at_least_clause(&mut self, min: usize, weighted_vars: &[(usize, Lit)]) {
+        let mut sum = WeightedSum::new(weighted_vars);
+        self.record_linear(weighted_vars, 1, min as i64);
+        if min > sum.total() {
+            self.unsatisfiable_clause();
+            return;
+        }
+        let var = self.sum_at_least_var(&mut sum, min);
+        self.or_clause(&[var]);
+    }
+
+    /// a var for whether the sum is at most `max`, encoded as a BDD over the terms
+    pub fn sum_at_most_var(&mut self, sum: &mut WeightedSum, max: usize) -> Lit {
+        match self.at_most_node(sum, 0, max) {
+            Node::Constant(value) => self.constant_var(value),
+            Node::Var(var) => var,
+        }
+    }
+    /// a var for whether the sum is at least `min`
+    pub fn sum_at_least_var(&mut self, sum: &mut WeightedSum, min: usize) -> Lit {
+        match min.checked_sub(1) {
+            Some(max) => !self.sum_at_most_var(sum, max),
+            None => self.constant_var(true),
+        }
+    }
+    /// whether the terms from index `i` on add up to at most `max`
+    fn at_most_node(&mut self, sum: &mut WeightedSum, i: usize, max: usize) -> Node {
+        if sum.remaining[i] <= max {
+            return Node::Constant(true);
+        }
+        if let Some(&var) = sum.nodes.get(&(i, max)) {
+            return Node::Var(var);
+        }
+        let (weight, var) = sum.terms[i];
+        let taken = match max.checked_sub(weight) {
+            Some(max) => self.at_most_node(sum, i + 1, max),
+            None => Node::Constant(false),
+        };
+        let skipped = self.at_most_node(sum, i + 1, max);
+        // taking a term never makes the sum smaller, so `taken` implies `skipped`
+        let node = match (taken, skipped) {
+            _ if taken == skipped => return taken,
+            (Node::Constant(false), Node::Constant(true)) => {
+                sum.nodes.insert((i, max), !var);
+                return Node::Var(!var);
+            }
+            (Node::Constant(false), Node::Var(skipped)) => self.and_var(&[!var, skipped]),
+            (Node::Var(taken), Node::Constant(true)) => self.or_var(&[!var, taken]),
+            (Node::Var(taken), Node::Var(skipped)) => {
+                // node == if var { taken } else { skipped }
+                let node = self.var();
+                self.or_clause(&[!node, !var, taken]);
+                self.or_clause(&[!node, var, skipped]);
+                self.or_clause(&[node, !var, !taken]);
+                self.or_clause(&[node, var, !skipped]);
+                node
+            }
+            _ => unreachable!("taking a term made the sum smaller"),
+        };
+        self.name_var(node, || {
+            format!("bdd: last {} terms at most {max}", sum.terms.len() - i)
+        });
+        sum.nodes.insert((i, max), node);
+        Node::Var(node)
+    }
+
+    /// keep `sign * sum >= bound` to write to OPB files, if recording
+    fn record_linear(&mut self, weighted_vars: &[(usize, Lit)], sign: i64, bound: i64) {
+        if self.clauses.is_some() {
+            let terms = weighted_vars
+                .iter()
+                .map(|&(weight, var)| (sign * weight as i64, var))
+                .collect();
+            self.linear_constraints
+                .push(LinearConstraint { terms, bound });
+        }
+    }
+
+    /// write the recorded clauses and weighted constraints in OPB format for pseudo-boolean solvers,
+    /// with a comment naming each described var, and an objective if given
+    pub fn write_opb(
+        &self,
+        out: &mut impl Write,
+        objective: Option<(Goal, &[(usize, Lit)])>,
+    ) -> io::Result<()> {
+        let clauses = self
+            .clauses
+            .as_ref()
+            .expect("clauses must be recorded to write them");
+        writeln!(
+            out,
+            "* #variable= {} #constraint= {}",
+            self.num_vars,
+            clauses.len() + self.linear_constraints.len()
+        )?;
+        for (var, name) in &self.names {
+            writeln!(out, "* x{} {}", var.to_dimacs(), name)?;
+        }
+        let format_terms = |terms: &mut dyn Iterator<Item = (i64, Lit)>| {
+            terms
+                .map(|(weight, var)| {
+                    let negated = if var.is_negative() { "~" } else { "" };
+                    format!("{weight:+} {negated}x{}", var.var().to_dimacs())
+                })
+                .join(" ")
+        };
+        if let Some((goal, weighted_vars)) = objective {
+            // OPB only minimizes, so maximizing minimizes the negative
+            let sign = match goal {
+                Goal::Minimize => 1,
+                Goal::Maximize => -1,
+            };
+            let mut terms = weighted_vars
+                .iter()
+                .map(|&(weight, var)| (sign * weight as i64, var));
+            writeln!(out, "min: {} ;", format_terms(&mut terms))?;
+        }
+        for clause in clauses {
+            let mut terms = clause.iter().map(|&var| (1, var));
+            writeln!(out, "{} >= 1 ;", format_terms(&mut terms))?;
+        }
+        for constraint in &self.linear_constraints {
+            let mut terms = constraint.terms.iter().copied();
+            writeln!(
+                out,
+                "{} >= {} ;",
+                format_terms(&mut terms),
+                constraint.bound
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::super::tests::all_solutions;
+    use super::*;
+
+    #[test]
+    fn weighted_bounds() {
+        let weights = [3, 0, 2, 5, 2];
+        let total: usize = weights.iter().sum();
+        let weight_of = |values: &[bool]| {
+            weights
+                .iter()
+                .zip(values)
+                .filter(|&(_, &value)| value)
+                .map(|(weight, _)| weight)
+                .sum::<usize>()
+        };
+        let assignments = (0..1 << weights.len())
+            .map(|bits: usize| (0..weights.len()).map(|i| bits >> i & 1 == 1).collect_vec())
+            .collect_vec();
+        for bound in 0..=total + 1 {
+            let mut p = SatProblem::new();
+            let vars = weights.iter().map(|_| p.var()).collect_vec();
+            let weighted_vars = weights
+                .iter()
+                .copied()
+                .zip(vars.iter().copied())
+                .collect_vec();
+            p.weighted_at_most_clause(bound, &weighted_vars);
+            let expected = assignments
+                .iter()
+                .filter(|values| weight_of(values) <= bound)
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            assert_eq!(all_solutions(p, &vars), expected, "at most {bound}");
+
+            let mut p = SatProblem::new();
+            let vars = weights.iter().map(|_| p.var()).collect_vec();
+            let weighted_vars = weights
+                .iter()
+                .copied()
+                .zip(vars.iter().copied())
+                .collect_vec();
+            p.weighted_at_least_clause(bound, &weighted_vars);
+            let expected = assignments
+                .iter()
+                .filter(|values| weight_of(values) >= bound)
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            assert_eq!(all_solutions(p, &vars), expected, "at least {bound}");
+        }
+    }
+
+    #[test]
+    fn shared_nodes() {
+        let mut p = SatProblem::new();
+        let vars = (0..4).map(|_| p.var()).collect_vec();
+        let mut sum = WeightedSum::new(&[(1, vars[0]), (2, vars[1]), (3, vars[2]), (4, vars[3])]);
+        assert_eq!(sum.total(), 10);
+        let at_most = p.sum_at_most_var(&mut sum, 5);
+        let at_least = p.sum_at_least_var(&mut sum, 6);
+        assert_eq!(at_least, !at_most);
+        p.or_clause(&[at_least]);
+        p.and_clause(&[vars[0], vars[1]]);
+        p.not_clause(vars[3]);
+        let solution = p.solve().unwrap();
+        assert_eq!(sum.value(&solution), 6);
+    }
+
+    #[test]
+    fn write_opb() {
+        let mut p = SatProblem::new();
+        p.record_clauses();
+        let a = p.var();
+        let b = p.var();
+        p.name_var(a, || "a".to_owned());
+        p.or_clause(&[a, !b]);
+        p.weighted_at_most_clause(2, &[(2, a), (1, b)]);
+
+        let mut out = vec![];
+        p.write_opb(&mut out, Some((Goal::Maximize, &[(3, a)])))
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines = text.lines().collect_vec();
+        assert!(lines[0].starts_with(&format!("* #variable= {} #constraint= ", p.num_vars)));
+        assert_eq!(lines[1], "* x1 a");
+        let objective = lines
+            .iter()
+            .position(|line| line.starts_with("min:"))
+            .unwrap();
+        assert_eq!(lines[objective], "min: -3 x1 ;");
+        assert_eq!(lines[objective + 1], "+1 x1 +1 ~x2 >= 1 ;");
+        assert_eq!(*lines.last().unwrap(), "-2 x1 -1 x2 >= -2 ;");
+    }
+}
@@ -0,0 +1,358 @@
+use crate::puzzle::*;
+use crate::solution::DoubledSolution;
+
+/// cubic bezier curve, from its first to its last control point
+pub type Cubic = [Vec2; 4];
+
+/// parameters of a knob, relative to the length of the side it is on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Knob {
+    /// position of the center of the knob along the side, from its start
+    pub neck: f64,
+    /// radius of the head of the knob along the side
+    pub width: f64,
+    /// radius of the head of the knob out from the side
+    pub height: f64,
+    /// distance from the side to the center of the head
+    pub stem: f64,
+}
+impl Knob {
+    /// the side from `(0, 0)` to `(1, 0)` with the knob sticking out towards positive y
+    fn profile(self) -> Vec<Cubic> {
+        let (c, w, h, s) = (self.neck, self.width, self.height, self.stem);
+        let neck = 0.5 * w;
+        // control distances for a quarter of an ellipse
+        let (kx, ky) = (0.55 * w, 0.55 * h);
+        let p = Vec2::new;
+        vec![
+            line(p(0.0, 0.0), p(c - neck, 0.0)),
+            [
+                p(c - neck, 0.0),
+                p(c - neck, 0.4 * s),
+                p(c - w, s - 0.5 * h),
+                p(c - w, s),
+            ],
+            [p(c - w, s), p(c - w, s + ky), p(c - kx, s + h), p(c, s + h)],
+            [p(c, s + h), p(c + kx, s + h), p(c + w, s + ky), p(c + w, s)],
+            [
+                p(c + w, s),
+                p(c + w, s - 0.5 * h),
+                p(c + neck, 0.4 * s),
+                p(c + neck, 0.0),
+            ],
+            line(p(c + neck, 0.0), p(1.0, 0.0)),
+        ]
+    }
+    /// how far the knob is from a centered round one, to use those first
+    fn oddness(self) -> f64 {
+        (self.neck - 0.5).abs() + (self.width - self.height).abs() + self.stem - self.height
+    }
+}
+
+/// the smallest difference between two knobs in one of their parameters,
+/// about a millimetre on pieces 25mm across
+pub const KNOB_GAP: f64 = 0.04;
+/// knobs keep this far from the ends of their side and stick out at most this far,
+/// so knobs on neighboring sides of a piece never touch
+pub const KNOB_CLEARANCE: f64 = 0.3;
+
+/// a different knob for each of `count` shapes, each differing from the others by at least `KNOB_GAP`.
+/// `symmetric` knobs are centered, so they still fit when pieces are turned over.
+/// fails if there aren't that many knobs that can be told apart
+pub fn distinct_knobs(count: usize, symmetric: bool) -> Result<Vec<Knob>, String> {
+    let steps = |from: f64, to: f64| {
+        (0..)
+            .map(move |i| from + KNOB_GAP * i as f64)
+            .take_while(move |&x| x <= to + 1e-9)
+    };
+    let offsets: &[i32] = if symmetric {
+        &[0]
+    } else {
+        &[0, -1, 1, -2, 2, -3, 3]
+    };
+    let mut knobs = vec![];
+    for width in steps(0.08, 0.2) {
+        for height in steps(0.06, 0.14) {
+            // neither much wider nor much taller than round
+            if width > 2.0 * height || height > 2.0 * width {
+                continue;
+            }
+            for stem in steps(height, KNOB_CLEARANCE - height) {
+                for &offset in offsets {
+                    let neck = 0.5 + KNOB_GAP * offset as f64;
+                    if (neck - 0.5).abs() + width <= 0.5 - KNOB_CLEARANCE + 1e-9 {
+                        knobs.push(Knob {
+                            neck,
+                            width,
+                            height,
+                            stem,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    if knobs.len() < count {
+        return Err(format!(
+            "the cut needs {count} knob shapes, but only {} can be told apart{}",
+            knobs.len(),
+            if symmetric {
+                " when pieces are turned over"
+            } else {
+                ""
+            }
+        ));
+    }
+    knobs.sort_by(|a, b| a.oddness().total_cmp(&b.oddness()));
+    knobs.truncate(count);
+    Ok(knobs)
+}
+
+/// the cut between the two points of an edge, with the knob sticking out of the piece with `tab_point`
+pub fn edge_curve(puzzle: &impl PuzzleGeometry, tab_point: PointKey, knob: Knob) -> Vec<Cubic> {
+    let (start, end) = puzzle.point_side(tab_point);
+    let along = end - start;
+    // sides go clockwise, so outwards is a quarter turn counter-clockwise
+    let outwards = -along.perp();
+    knob.profile()
+        .into_iter()
+        .map(|cubic| cubic.map(|p| start + along * p.x + outwards * p.y))
+        .collect()
+}
+
+/// every cut needed to make the puzzle: a knob for each edge, with the same knob for edges that must match
+/// and a different one for edges that must not, and a straight line for each border side
+pub fn cut_paths(
+    puzzle: &impl PuzzleGeometry,
+    solution: &DoubledSolution,
+) -> Result<Vec<Vec<Cubic>>, String> {
+    let knobs = distinct_knobs(solution.edge_pairs.len(), solution.any_flipped())?;
+    let mut paths = vec![];
+    for (&(edge_a, edge_b), &knob) in solution.edge_pairs.iter().zip(&knobs) {
+        paths.push(edge_curve(puzzle, solution.tab_points[edge_a.0], knob));
+        if edge_b != edge_a {
+            paths.push(edge_curve(puzzle, solution.tab_points[edge_b.0], knob));
+        }
+    }
+    for piece in puzzle_pieces(puzzle) {
+        let corners = puzzle.piece_corners(piece);
+        let point_sides = puzzle_points_on_piece(puzzle, piece)
+            .map(|point| puzzle.point_side(point))
+            .collect::<Vec<_>>();
+        for (i, &start) in corners.iter().enumerate() {
+            let end = corners[(i + 1) % corners.len()];
+            let is_point = point_sides
+                .iter()
+                .any(|&(a, b)| (a - start).length() < 1e-9 && (b - end).length() < 1e-9);
+            if !is_point {
+                paths.push(vec![line(start, end)]);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// which edges share a shape, read back from the printed output of a solution
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeShapes {
+    /// shape class of each edge
+    pub classes: Vec<usize>,
+    /// point with the tab of each edge, if given
+    pub tab_points: Option<Vec<PointKey>>,
+    /// intended destination of source points, if given
+    pub intended: Option<Vec<(PointKey, PointKey)>>,
+}
+impl EdgeShapes {
+    /// parse the first `edge matching:` line, and `tabs:` and `piece dest:` lines if present.
+    /// edges that aren't matched with another edge have their own shape
+    pub fn parse(puzzle: &impl Puzzle, text: &str) -> Result<Self, String> {
+        let find_line = |prefix: &str| text.lines().find_map(|line| line.strip_prefix(prefix));
+        let edge = |name: &str| {
+            puzzle_find_edge(puzzle, name).ok_or_else(|| format!("unknown edge {name:?}"))
+        };
+        let point = |name: &str| {
+            puzzle_find_point(puzzle, name).ok_or_else(|| format!("unknown point {name:?}"))
+        };
+
+        let matching = find_line("edge matching:").ok_or("missing edge matching line")?;
+        let mut classes = puzzle_edges(puzzle).map(|edge| edge.0).collect::<Vec<_>>();
+        for group in matching.split_whitespace() {
+            let edges = group.split('=').map(edge).collect::<Result<Vec<_>, _>>()?;
+            let class = classes[edges[0].0];
+            for edge in edges {
+                let old_class = classes[edge.0];
+                for c in &mut classes {
+                    if *c == old_class {
+                        *c = class;
+                    }
+                }
+            }
+        }
+
+        let tab_points = find_line("tabs:")
+            .map(|tabs| {
+                let points = tabs
+                    .split_whitespace()
+                    .map(point)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut tab_points = vec![None; puzzle.num_edges()];
+                for point in points {
+                    tab_points[puzzle.point_edge(point).0] = Some(point);
+                }
+                tab_points
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| "tabs line must have a tab for every edge".to_owned())
+            })
+            .transpose()?;
+
+        let intended = find_line("piece dest:")
+            .map(|dests| {
+                dests
+                    .split_whitespace()
+                    .map(|dest| {
+                        let (src, dest) = dest
+                            .split_once("=>")
+                            .ok_or_else(|| format!("invalid piece dest {dest:?}"))?;
+                        Ok((point(src)?, point(dest)?))
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .transpose()?;
+
+        Ok(Self {
+            classes,
+            tab_points,
+            intended,
+        })
+    }
+
+    /// whether the sides of two points can be put together
+    pub fn fits(&self, puzzle: &impl Puzzle, point_a: PointKey, point_b: PointKey) -> bool {
+        let edge_a = puzzle.point_edge(point_a);
+        let edge_b = puzzle.point_edge(point_b);
+        self.classes[edge_a.0] == self.classes[edge_b.0]
+            && self.tab_points.as_ref().is_none_or(|tab_points| {
+                (tab_points[edge_a.0] == point_a) != (tab_points[edge_b.0] == point_b)
+            })
+    }
+}
+
+fn line(start: Vec2, end: Vec2) -> Cubic {
+    [
+        start,
+        start + (end - start) * (1.0 / 3.0),
+        start + (end - start) * (2.0 / 3.0),
+        end,
+    ]
+}
+
+/// point on a cubic bezier curve, `t` from 0 to 1
+pub fn cubic_point(cubic: &Cubic, t: f64) -> Vec2 {
+    let u = 1.0 - t;
+    cubic[0] * (u * u * u)
+        + cubic[1] * (3.0 * u * u * t)
+        + cubic[2] * (3.0 * u * t * t)
+        + cubic[3] * (t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knobs_are_distinct() {
+        for symmetric in [false, true] {
+            let knobs = distinct_knobs(20, symmetric).unwrap();
+            for (i, a) in knobs.iter().enumerate() {
+                assert!(a.neck - a.width >= KNOB_CLEARANCE - 1e-9);
+                assert!(a.neck + a.width <= 1.0 - KNOB_CLEARANCE + 1e-9);
+                assert!(a.stem + a.height <= KNOB_CLEARANCE + 1e-9);
+                for b in &knobs[..i] {
+                    let gap = [
+                        a.neck - b.neck,
+                        a.width - b.width,
+                        a.height - b.height,
+                        a.stem - b.stem,
+                    ]
+                    .into_iter()
+                    .map(f64::abs)
+                    .fold(0.0, f64::max);
+                    assert!(gap >= KNOB_GAP - 1e-9, "{a:?} looks like {b:?}");
+                }
+            }
+        }
+        assert!(distinct_knobs(20, true)
+            .unwrap()
+            .iter()
+            .all(|knob| knob.neck == 0.5));
+        assert!(distinct_knobs(100, false).is_ok());
+        assert!(distinct_knobs(100, true).is_err());
+    }
+
+    #[test]
+    fn knob_sticks_out_of_tab_piece() {
+        let puzzle = SquarePuzzle::new(1, 2);
+        // the only edge, seen from a1
+        let point = PointKey(0);
+        let knob = Knob {
+            neck: 0.5,
+            width: 0.1,
+            height: 0.1,
+            stem: 0.1,
+        };
+        let curve = edge_curve(&puzzle, point, knob);
+        assert_eq!(curve[0][0], Vec2::new(1.0, 0.0));
+        assert_eq!(curve.last().unwrap()[3], Vec2::new(1.0, 1.0));
+        let tip = curve[2][3];
+        assert!(tip.x > 1.0 && (tip.y - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parse_edge_shapes() {
+        let puzzle = SquarePuzzle::new(1, 4);
+        let text = "\
+found solution 1 in 1ms
+piece dest: a1>=>a1> a2<=>a3> a3<=>a2> a4<=>a4<
+edge matching: a1|=a3|
+tabs: a1> a2> a4<
+";
+        let shapes = EdgeShapes::parse(&puzzle, text).unwrap();
+        assert_eq!(shapes.classes, vec![0, 1, 0]);
+        assert_eq!(
+            shapes.tab_points,
+            Some(vec![PointKey(0), PointKey(2), PointKey(5)])
+        );
+        assert_eq!(
+            shapes.intended.as_ref().unwrap()[1],
+            (PointKey(1), PointKey(4))
+        );
+        // a1> fits against a3> in the second assembly, but not a4< which is also a tab
+        assert!(shapes.fits(&puzzle, PointKey(0), PointKey(4)));
+        assert!(!shapes.fits(&puzzle, PointKey(0), PointKey(5)));
+        assert!(!shapes.fits(&puzzle, PointKey(0), PointKey(3)));
+
+        assert!(EdgeShapes::parse(&puzzle, "tabs: a1>").is_err());
+        assert!(EdgeShapes::parse(&puzzle, "edge matching: a1|=z9|").is_err());
+        let shapes = EdgeShapes::parse(&puzzle, "edge matching: a1|=a2|=a3|").unwrap();
+        assert_eq!(shapes.classes, vec![0, 0, 0]);
+        assert!(shapes.fits(&puzzle, PointKey(0), PointKey(5)));
+    }
+
+    #[test]
+    fn strip_cuts() {
+        let puzzle = SquarePuzzle::new(1, 4);
+        let solution = DoubledSolution::new(
+            &puzzle,
+            &[
+                (PointKey(0), false),
+                (PointKey(4), false),
+                (PointKey(2), false),
+                (PointKey(5), false),
+            ],
+        );
+        let paths = cut_paths(&puzzle, &solution).unwrap();
+        // 3 edges, 4 top and bottom sides, 2 ends
+        assert_eq!(paths.len(), 3 + 8 + 2);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::puzzle::*;
+
+/// a second assembly of a puzzle, decoded from a sat solution
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoubledSolution {
+    /// where each source point lands in the second assembly
+    point_dest: Vec<PointKey>,
+    /// whether each source piece is turned over in the second assembly
+    flipped: Vec<bool>,
+    /// pairs of edges that must have the same shape, smaller edge first.
+    /// an unmatched edge is paired with itself
+    pub edge_pairs: Vec<(EdgeKey, EdgeKey)>,
+    /// point with the tab of each edge
+    pub tab_points: Vec<PointKey>,
+}
+impl DoubledSolution {
+    /// decode from where the `arbitrary_point_on_piece` of each source piece lands and whether it is turned over.
+    /// tabs are put on arbitrary but consistent points, use `with_tab_points` to choose them
+    pub fn new<P: Puzzle>(puzzle: &P, placements: &[(PointKey, bool)]) -> Self {
+        Self::between(puzzle, puzzle, placements)
+    }
+    /// like `new`, but the pieces are put together on `dest`, a board with a different outline.
+    /// destination points are points of `dest`, everything else is in terms of `puzzle`
+    pub fn between<P: Puzzle>(puzzle: &P, dest: &P, placements: &[(PointKey, bool)]) -> Self {
+        assert_eq!(placements.len(), puzzle.num_pieces());
+        assert_eq!(dest.num_points(), puzzle.num_points());
+        let mut point_dest = vec![PointKey(usize::MAX); puzzle.num_points()];
+        for (src_piece, &(dest_point, flipped)) in puzzle_pieces(puzzle).zip(placements) {
+            let mut dest_point = dest_point;
+            for src_point in puzzle_points_on_piece(puzzle, src_piece) {
+                point_dest[src_point.0] = dest_point;
+                dest_point = if flipped {
+                    dest.previous_point_on_piece(dest_point)
+                } else {
+                    dest.next_point_on_piece(dest_point)
+                };
+            }
+        }
+        let mut point_src = vec![PointKey(usize::MAX); puzzle.num_points()];
+        for (src_point, dest_point) in puzzle_points(puzzle).zip(&point_dest) {
+            assert_eq!(point_src[dest_point.0].0, usize::MAX, "pieces overlap");
+            point_src[dest_point.0] = src_point;
+        }
+
+        let mut edge_pairs = vec![];
+        let mut tab_points = vec![PointKey(usize::MAX); puzzle.num_edges()];
+        for edge_a in puzzle_edges(puzzle) {
+            let point_a = puzzle.arbitrary_point_on_edge(edge_a);
+            // the point that ends up against `point_a`
+            let point_b = point_src[dest.other_point_on_edge(point_dest[point_a.0]).0];
+            let edge_b = puzzle.point_edge(point_b);
+            if edge_b < edge_a {
+                continue;
+            }
+            edge_pairs.push((edge_a, edge_b));
+            tab_points[edge_a.0] = point_a;
+            // the tab of `edge_b` is on the point that takes the place of `point_a`
+            tab_points[edge_b.0] = puzzle.other_point_on_edge(point_b);
+        }
+        Self {
+            point_dest,
+            flipped: placements.iter().map(|&(_, flipped)| flipped).collect(),
+            edge_pairs,
+            tab_points,
+        }
+    }
+
+    /// use tabs from a solution that chose them, they must be consistent with the edge pairs
+    pub fn with_tab_points(mut self, tab_points: Vec<PointKey>) -> Self {
+        self.tab_points = tab_points;
+        self
+    }
+
+    pub fn point_dest(&self, src_point: PointKey) -> PointKey {
+        self.point_dest[src_point.0]
+    }
+    pub fn is_flipped(&self, src_piece: PieceKey) -> bool {
+        self.flipped[src_piece.0]
+    }
+    pub fn any_flipped(&self) -> bool {
+        self.flipped.iter().any(|&flipped| flipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swapped_strip() {
+        // a1 a2 a3 a4 becomes a1 a3 a2 a4 with the middle pieces turned half way around
+        let puzzle = SquarePuzzle::new(1, 4);
+        let point = |name: &str| {
+            puzzle_points(&puzzle)
+                .find(|&point| puzzle.format_point(point) == name)
+                .unwrap()
+        };
+        let solution = DoubledSolution::new(
+            &puzzle,
+            &[
+                (point("a1>"), false),
+                (point("a3>"), false),
+                (point("a2>"), false),
+                (point("a4<"), false),
+            ],
+        );
+        assert_eq!(solution.point_dest(point("a3>")), point("a2<"));
+        // a1| matches a3|, and a2| stays together
+        assert_eq!(
+            solution.edge_pairs,
+            vec![(EdgeKey(0), EdgeKey(2)), (EdgeKey(1), EdgeKey(1))]
+        );
+        assert_eq!(solution.tab_points[0], point("a1>"));
+        assert_eq!(solution.tab_points[2], point("a4<"));
+        assert!(!solution.any_flipped());
+    }
+
+    #[test]
+    fn strip_into_column() {
+        // the same swap, but the pieces are stacked into a column
+        let puzzle = SquarePuzzle::new(1, 4);
+        let dest = SquarePuzzle::new(4, 1);
+        let solution = DoubledSolution::between(
+            &puzzle,
+            &dest,
+            &[
+                (puzzle_find_point(&dest, "a1v").unwrap(), false),
+                (puzzle_find_point(&dest, "c1v").unwrap(), false),
+                (puzzle_find_point(&dest, "b1v").unwrap(), false),
+                (puzzle_find_point(&dest, "d1^").unwrap(), false),
+            ],
+        );
+        let a3 = puzzle_find_point(&puzzle, "a3>").unwrap();
+        assert_eq!(
+            solution.point_dest(a3),
+            puzzle_find_point(&dest, "b1^").unwrap()
+        );
+        assert_eq!(
+            solution.edge_pairs,
+            vec![(EdgeKey(0), EdgeKey(2)), (EdgeKey(1), EdgeKey(1))]
+        );
+    }
+}